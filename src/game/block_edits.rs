@@ -0,0 +1,226 @@
+use crate::network::{BlockAction, BlockCoordinate};
+
+use core::block::Block;
+use core::chunk::ChunkGridCoordinate;
+use core::world::World;
+use std::collections::HashMap;
+
+struct PendingEdit {
+    coords: BlockCoordinate,
+    /// block left by the edit
+    block: Block,
+    /// block to restore if the server rejects the edit
+    previous: Block,
+}
+
+/// Block edits applied to the world before the server confirmed them, so they can be rolled
+/// back when it does not.
+pub struct BlockEdits {
+    next_id: u32,
+    pending: HashMap<u32, PendingEdit>,
+}
+
+impl BlockEdits {
+    pub fn new() -> Self {
+        Self {
+            next_id: 0,
+            pending: HashMap::new(),
+        }
+    }
+
+    /// applies the action locally and returns the id of the edit to send to the server,
+    /// `None` if the block is not loaded or the action changes nothing
+    pub fn apply(
+        &mut self,
+        world: &mut World,
+        coords: BlockCoordinate,
+        action: BlockAction,
+    ) -> Option<u32> {
+        let block = action.block();
+        let previous = coords.set_block(world, block)?;
+
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1);
+        self.pending.insert(
+            id,
+            PendingEdit {
+                coords,
+                block,
+                previous,
+            },
+        );
+
+        Some(id)
+    }
+
+    /// applies the pending edits again on a chunk the server just sent, which does not have
+    /// them yet, and returns the chunks to remesh
+    pub fn reapply(
+        &mut self,
+        world: &mut World,
+        chunk: ChunkGridCoordinate,
+    ) -> Vec<ChunkGridCoordinate> {
+        let mut ids: Vec<u32> = self
+            .pending
+            .iter()
+            .filter(|(_, edit)| edit.coords.chunk() == chunk)
+            .map(|(id, _)| *id)
+            .collect();
+        ids.sort_unstable();
+
+        let mut meshes = Vec::new();
+        for id in ids {
+            let edit = self.pending.get_mut(&id).unwrap();
+
+            if let Some(previous) = edit.coords.set_block(world, edit.block) {
+                edit.previous = previous;
+                meshes.append(&mut edit.coords.meshes());
+            }
+        }
+
+        meshes
+    }
+
+    pub fn accepted(&mut self, id: u32) {
+        self.pending.remove(&id);
+    }
+
+    /// forgets the edit and returns the coordinate and block to restore
+    pub fn rejected(&mut self, id: u32) -> Option<(BlockCoordinate, Block)> {
+        self.pending
+            .remove(&id)
+            .map(|edit| (edit.coords, edit.previous))
+    }
+
+    /// keeps rollbacks from undoing changes the server made since the edit was applied
+    pub fn changed(&mut self, coords: BlockCoordinate, block: Block) {
+        for edit in self.pending.values_mut() {
+            if edit.coords == coords {
+                edit.previous = block;
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.pending.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::AIR;
+
+    use core::world::WorldCoordinate;
+
+    const STONE: Block = Block { id: 1 };
+    const DIRT: Block = Block { id: 2 };
+
+    fn world() -> World {
+        let mut world = World::new();
+        world.load_around(vec![WorldCoordinate {
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+        }]);
+
+        world
+    }
+
+    /// puts a known block there whatever the world generated
+    fn reset(world: &mut World, coords: BlockCoordinate, block: Block) {
+        coords.set_block(world, block);
+        assert_eq!(coords.block(world).unwrap().id, block.id);
+    }
+
+    fn at(x: i64) -> BlockCoordinate {
+        BlockCoordinate { x, y: 200, z: 0 }
+    }
+
+    #[test]
+    fn rejected_edits_are_rolled_back() {
+        let mut world = world();
+        let mut edits = BlockEdits::new();
+        reset(&mut world, at(1), STONE);
+
+        let id = edits.apply(&mut world, at(1), BlockAction::Break).unwrap();
+        assert_eq!(at(1).block(&world).unwrap().id, AIR.id);
+
+        let (coords, previous) = edits.rejected(id).unwrap();
+        assert_eq!(coords, at(1));
+        assert_eq!(previous.id, STONE.id);
+        assert!(edits.rejected(id).is_none());
+    }
+
+    #[test]
+    fn accepted_edits_are_forgotten() {
+        let mut world = world();
+        let mut edits = BlockEdits::new();
+        reset(&mut world, at(1), AIR);
+
+        let id = edits
+            .apply(&mut world, at(1), BlockAction::Place(DIRT))
+            .unwrap();
+        edits.accepted(id);
+
+        assert!(edits.rejected(id).is_none());
+        assert_eq!(at(1).block(&world).unwrap().id, DIRT.id);
+    }
+
+    #[test]
+    fn edits_changing_nothing_are_not_sent() {
+        let mut world = world();
+        let mut edits = BlockEdits::new();
+        reset(&mut world, at(1), AIR);
+
+        assert!(edits.apply(&mut world, at(1), BlockAction::Break).is_none());
+        assert!(edits.pending.is_empty());
+    }
+
+    #[test]
+    fn rollbacks_restore_what_the_server_changed_since() {
+        let mut world = world();
+        let mut edits = BlockEdits::new();
+        reset(&mut world, at(1), AIR);
+        reset(&mut world, at(2), AIR);
+
+        let id = edits
+            .apply(&mut world, at(1), BlockAction::Place(DIRT))
+            .unwrap();
+        edits.changed(at(1), STONE);
+        edits.changed(at(2), DIRT);
+
+        let (_, previous) = edits.rejected(id).unwrap();
+        assert_eq!(previous.id, STONE.id);
+    }
+
+    #[test]
+    fn pending_edits_are_applied_again_on_received_chunks() {
+        let mut world = world();
+        let mut edits = BlockEdits::new();
+        let other = BlockCoordinate {
+            x: -1,
+            y: 200,
+            z: 0,
+        };
+        reset(&mut world, at(0), AIR);
+        reset(&mut world, other, AIR);
+
+        let id = edits
+            .apply(&mut world, at(0), BlockAction::Place(DIRT))
+            .unwrap();
+        edits.apply(&mut world, other, BlockAction::Place(DIRT));
+
+        // the chunk sent by the server has stone there instead
+        reset(&mut world, at(0), STONE);
+        reset(&mut world, other, STONE);
+
+        let meshes = edits.reapply(&mut world, at(0).chunk());
+        assert_eq!(meshes, at(0).meshes());
+        assert_eq!(at(0).block(&world).unwrap().id, DIRT.id);
+        assert_eq!(other.block(&world).unwrap().id, STONE.id);
+
+        let (_, previous) = edits.rejected(id).unwrap();
+        assert_eq!(previous.id, STONE.id);
+    }
+}
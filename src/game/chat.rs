@@ -0,0 +1,66 @@
+use crate::input::LineBuffer;
+use crate::network::{validate_message, ChatError, RateLimiter};
+
+use std::collections::VecDeque;
+use std::time::Instant;
+
+const MAX_HISTORY: usize = 100;
+
+pub struct ChatMessage {
+    /// author of the message, `None` for notices from the game or the server
+    pub name: Option<String>,
+    pub text: String,
+    pub received: Instant,
+}
+
+/// Messages received so far along with the one being typed.
+pub struct Chat {
+    history: VecDeque<ChatMessage>,
+    limiter: RateLimiter,
+    draft: Option<LineBuffer>,
+}
+
+impl Chat {
+    pub fn new() -> Self {
+        Self {
+            history: VecDeque::new(),
+            limiter: RateLimiter::new(),
+            draft: None,
+        }
+    }
+
+    pub fn push(&mut self, name: Option<String>, text: String) {
+        match name.as_ref() {
+            Some(name) => info!("<chat> {}: {}", name, text),
+            None => info!("<chat> {}", text),
+        }
+
+        if self.history.len() >= MAX_HISTORY {
+            self.history.pop_front();
+        }
+
+        self.history.push_back(ChatMessage {
+            name,
+            text,
+            received: Instant::now(),
+        });
+    }
+
+    /// oldest message first
+    pub fn history(&self) -> &VecDeque<ChatMessage> {
+        &self.history
+    }
+
+    pub fn validate(&mut self, text: &str) -> Result<(), ChatError> {
+        validate_message(text, &mut self.limiter)
+    }
+
+    /// message being typed, `None` when the chat is closed
+    pub fn draft(&self) -> Option<&LineBuffer> {
+        self.draft.as_ref()
+    }
+
+    pub fn set_draft(&mut self, draft: Option<LineBuffer>) {
+        self.draft = draft;
+    }
+}
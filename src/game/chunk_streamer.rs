@@ -0,0 +1,104 @@
+use core::chunk::{ChunkGridCoordinate, CHUNK_DEPTH, CHUNK_WIDTH};
+use core::world::{World, WorldCoordinate, LOAD_DISTANCE};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// rough size of a serialized chunk, used to estimate the bandwidth taken by a request
+const ESTIMATED_CHUNK_SIZE: f64 = 64.0 * 1024.0;
+/// bytes per second we allow chunk requests to pull from the server
+const MAX_BANDWIDTH: f64 = 1024.0 * 1024.0;
+const MAX_IN_FLIGHT: usize = 16;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+/// chunks further than this from the player are unloaded, a bit past the load distance so
+/// chunks on the edge don't flicker as the player walks back and forth
+const UNLOAD_DISTANCE: i64 = LOAD_DISTANCE as i64 + 1;
+
+/// distance in chunks between a chunk and the chunk holding `position`
+fn distance(coords: &ChunkGridCoordinate, position: WorldCoordinate) -> i64 {
+    let center_x = (position.x / CHUNK_WIDTH as f32).floor() as i64;
+    let center_z = (position.z / CHUNK_DEPTH as f32).floor() as i64;
+
+    (coords.x - center_x).abs().max((coords.z - center_z).abs())
+}
+
+/// Decides which chunks to ask the server for, closest to the player first, without
+/// requesting more than the connection can bring in.
+pub struct ChunkStreamer {
+    requested: HashMap<ChunkGridCoordinate, Instant>,
+    budget: f64,
+    last_update: Instant,
+}
+
+impl ChunkStreamer {
+    pub fn new() -> Self {
+        Self {
+            requested: HashMap::new(),
+            budget: MAX_BANDWIDTH,
+            last_update: Instant::now(),
+        }
+    }
+
+    /// unloads chunks out of range and returns the chunks to request this frame
+    pub fn update(
+        &mut self,
+        world: &mut World,
+        position: WorldCoordinate,
+    ) -> Vec<ChunkGridCoordinate> {
+        self.budget = (self.budget + self.last_update.elapsed().as_secs_f64() * MAX_BANDWIDTH)
+            .min(MAX_BANDWIDTH);
+        self.last_update = Instant::now();
+
+        let center_x = (position.x / CHUNK_WIDTH as f32).floor() as i64;
+        let center_z = (position.z / CHUNK_DEPTH as f32).floor() as i64;
+
+        world
+            .chunks
+            .retain(|coords, _| distance(coords, position) <= UNLOAD_DISTANCE);
+
+        self.requested
+            .retain(|_, requested| requested.elapsed() < REQUEST_TIMEOUT);
+
+        let load_distance = LOAD_DISTANCE as i64;
+        let mut missing = Vec::new();
+        for x in (center_x - load_distance)..=(center_x + load_distance) {
+            for z in (center_z - load_distance)..=(center_z + load_distance) {
+                let coords = ChunkGridCoordinate::new(x, z);
+
+                if !world.chunks.contains_key(&coords) && !self.requested.contains_key(&coords) {
+                    missing.push(coords);
+                }
+            }
+        }
+
+        missing.sort_by_key(|coords| {
+            let dx = coords.x - center_x;
+            let dz = coords.z - center_z;
+            dx * dx + dz * dz
+        });
+
+        let mut requests = Vec::new();
+        for coords in missing {
+            if self.requested.len() >= MAX_IN_FLIGHT || self.budget < ESTIMATED_CHUNK_SIZE {
+                break;
+            }
+
+            self.budget -= ESTIMATED_CHUNK_SIZE;
+            self.requested.insert(coords, Instant::now());
+            requests.push(coords);
+        }
+
+        requests
+    }
+
+    /// returns whether the chunk should be kept, the player may have moved away since it was
+    /// requested
+    pub fn received(&mut self, coords: ChunkGridCoordinate, position: WorldCoordinate) -> bool {
+        self.requested.remove(&coords);
+        distance(&coords, position) <= UNLOAD_DISTANCE
+    }
+
+    /// forgets in-flight requests, to call when the connection is lost
+    pub fn clear(&mut self) {
+        self.requested.clear();
+    }
+}
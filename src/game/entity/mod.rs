@@ -1,3 +1,6 @@
 mod player;
+mod snapshot;
 
-pub use self::player::{Player, PlayerId};
+pub use self::player::Player;
+pub use self::snapshot::{InterpolationSettings, SnapshotBuffer};
+pub use crate::network::PlayerId;
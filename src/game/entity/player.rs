@@ -1,43 +1,80 @@
-use crate::game::NETWORK_UPDATE_TIMEOUT;
+use crate::game::entity::{InterpolationSettings, SnapshotBuffer};
 
 use core::world::WorldCoordinate;
-use std::time::Instant;
-
-pub type PlayerId = u128;
+use math::vector::Vector3;
 
 pub struct Player {
+    name: Option<String>,
+    /// round trip time to the server in milliseconds, as reported by the server
+    latency: Option<u16>,
     position: WorldCoordinate,
-    start_position: WorldCoordinate,
-    target_position: WorldCoordinate,
-    last_update: Instant,
+    euler_angles: Vector3,
+    snapshots: SnapshotBuffer,
 }
 
 impl Player {
-    pub fn new() -> Self {
+    pub fn new(settings: InterpolationSettings) -> Self {
         Self {
+            name: None,
+            latency: None,
             position: WorldCoordinate::zero(),
-            start_position: WorldCoordinate::zero(),
-            target_position: WorldCoordinate::zero(),
-            last_update: Instant::now(),
+            euler_angles: Vector3::zero(),
+            snapshots: SnapshotBuffer::new(settings),
         }
     }
 
+    /// display name, `None` until the server tells it
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn set_name(&mut self, name: String) {
+        self.name = Some(name);
+    }
+
+    pub fn latency(&self) -> Option<u16> {
+        self.latency
+    }
+
+    pub fn set_latency(&mut self, latency: u16) {
+        self.latency = Some(latency);
+    }
+
     pub fn position(&self) -> WorldCoordinate {
         self.position
     }
 
+    /// where the player looks, pitch around x and yaw around y in degrees
+    pub fn euler_angles(&self) -> Vector3 {
+        self.euler_angles
+    }
+
+    pub fn set_interpolation_settings(&mut self, settings: InterpolationSettings) {
+        self.snapshots.set_settings(settings);
+    }
+
+    /// queues a state sent by the server at `timestamp` milliseconds, server time
+    pub fn push_snapshot(
+        &mut self,
+        timestamp: u64,
+        position: WorldCoordinate,
+        euler_angles: Vector3,
+    ) {
+        self.snapshots.push(timestamp, position, euler_angles);
+    }
+
+    /// queues a position that came without a server timestamp
     pub fn set_position(&mut self, position: WorldCoordinate) {
-        self.start_position = self.position;
-        self.target_position = position;
-        self.last_update = Instant::now();
+        self.snapshots.push_now(position);
     }
 
-    pub fn update(&mut self) {
-        let percent = (self.last_update.elapsed().as_secs_f32() * 1000.0
-            / (2 * NETWORK_UPDATE_TIMEOUT) as f32)
-            .min(1.0);
+    /// moves the player along its snapshots by `time_delta` seconds
+    pub fn update(&mut self, time_delta: f64) {
+        self.snapshots.advance(time_delta);
 
-        self.position =
-            self.start_position + (self.target_position - self.start_position) * percent;
+        if let Some((position, euler_angles)) = self.snapshots.sample() {
+            self.position = position;
+            self.euler_angles = euler_angles;
+        }
     }
 }
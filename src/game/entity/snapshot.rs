@@ -0,0 +1,279 @@
+use core::world::WorldCoordinate;
+use math::vector::Vector3;
+use std::collections::VecDeque;
+use std::time::Duration;
+
+const MAX_SNAPSHOTS: usize = 64;
+/// how fast the server clock estimate follows samples that suggest a larger latency
+const CLOCK_SMOOTHING: f64 = 0.05;
+
+#[derive(Debug, Copy, Clone)]
+pub struct InterpolationSettings {
+    /// how far in the past remote players are rendered, should cover a couple of updates
+    pub delay: Duration,
+    /// how long we keep moving a player along its last velocity when snapshots stop coming
+    pub max_extrapolation: Duration,
+}
+
+impl Default for InterpolationSettings {
+    fn default() -> Self {
+        Self {
+            delay: Duration::from_millis(100),
+            max_extrapolation: Duration::from_millis(250),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Snapshot {
+    /// server time in milliseconds
+    timestamp: u64,
+    position: WorldCoordinate,
+    /// euler angles in degrees, pitch around x and yaw around y
+    euler_angles: Vector3,
+}
+
+/// angle between `from` and `to` in degrees going the shortest way around
+fn angle_delta(from: f32, to: f32) -> f32 {
+    (to - from + 180.0).rem_euclid(360.0) - 180.0
+}
+
+/// Jitter buffer of server timestamped positions and orientations. Positions are sampled `delay` behind the
+/// estimated server time so there is usually a snapshot on each side to interpolate between.
+/// Local time only moves forward with `advance`, so replays can pause it or speed it up.
+pub struct SnapshotBuffer {
+    settings: InterpolationSettings,
+    snapshots: VecDeque<Snapshot>,
+    /// milliseconds since the buffer was created
+    local_time: f64,
+    /// estimated `server time - local time` in milliseconds
+    clock_offset: Option<f64>,
+}
+
+impl SnapshotBuffer {
+    pub fn new(settings: InterpolationSettings) -> Self {
+        Self {
+            settings,
+            snapshots: VecDeque::new(),
+            local_time: 0.0,
+            clock_offset: None,
+        }
+    }
+
+    pub fn set_settings(&mut self, settings: InterpolationSettings) {
+        self.settings = settings;
+    }
+
+    /// moves local time forward by `time_delta` seconds
+    pub fn advance(&mut self, time_delta: f64) {
+        self.local_time += time_delta.max(0.0) * 1000.0;
+    }
+
+    /// estimated server time in milliseconds at which positions are currently sampled
+    fn render_time(&self) -> Option<f64> {
+        let offset = self.clock_offset?;
+        Some(self.local_time + offset - self.settings.delay.as_secs_f64() * 1000.0)
+    }
+
+    pub fn push(&mut self, timestamp: u64, position: WorldCoordinate, euler_angles: Vector3) {
+        let sample = timestamp as f64 - self.local_time;
+        self.clock_offset = Some(match self.clock_offset {
+            Some(offset) if sample < offset => offset + (sample - offset) * CLOCK_SMOOTHING,
+            _ => sample,
+        });
+
+        // a snapshot older than the one we are already interpolating from is useless
+        if let Some(first) = self.snapshots.front() {
+            if timestamp < first.timestamp {
+                return;
+            }
+        }
+
+        let index = self
+            .snapshots
+            .iter()
+            .position(|snapshot| snapshot.timestamp >= timestamp)
+            .unwrap_or(self.snapshots.len());
+
+        if self
+            .snapshots
+            .get(index)
+            .map_or(false, |snapshot| snapshot.timestamp == timestamp)
+        {
+            return;
+        }
+
+        self.snapshots.insert(
+            index,
+            Snapshot {
+                timestamp,
+                position,
+                euler_angles,
+            },
+        );
+
+        if self.snapshots.len() > MAX_SNAPSHOTS {
+            self.snapshots.pop_front();
+        }
+    }
+
+    /// pushes a position that came without a timestamp nor orientation, as if it was sent right
+    /// now while looking the same way as before
+    pub fn push_now(&mut self, position: WorldCoordinate) {
+        let offset = self.clock_offset.unwrap_or(0.0);
+        let timestamp = (self.local_time + offset).max(0.0) as u64;
+        let euler_angles = self
+            .snapshots
+            .back()
+            .map_or(Vector3::zero(), |snapshot| snapshot.euler_angles);
+
+        self.push(timestamp, position, euler_angles);
+    }
+
+    /// position and euler angles at the current render time, `None` until the first snapshot
+    /// arrives
+    pub fn sample(&mut self) -> Option<(WorldCoordinate, Vector3)> {
+        let render_time = self.render_time()?;
+
+        // drop snapshots we went past, keeping the one right before the render time
+        while self.snapshots.len() > 2 && self.snapshots[1].timestamp as f64 <= render_time {
+            self.snapshots.pop_front();
+        }
+
+        let first = *self.snapshots.front()?;
+
+        if render_time <= first.timestamp as f64 {
+            return Some((first.position, first.euler_angles));
+        }
+
+        let second = match self.snapshots.get(1) {
+            Some(second) => *second,
+            None => return Some((first.position, first.euler_angles)),
+        };
+
+        let span = (second.timestamp - first.timestamp) as f64;
+        let mut t = (render_time - first.timestamp as f64) / span;
+
+        if t > 1.0 {
+            // ran out of snapshots, keep going along the last known velocity for a little while
+            let max_extrapolation = self.settings.max_extrapolation.as_secs_f64() * 1000.0;
+            t = t.min(1.0 + max_extrapolation / span);
+        }
+
+        // heads are not extrapolated, they would keep spinning
+        let s = t.min(1.0) as f32;
+        let euler_angles = Vector3 {
+            x: first.euler_angles.x + (second.euler_angles.x - first.euler_angles.x) * s,
+            y: first.euler_angles.y + angle_delta(first.euler_angles.y, second.euler_angles.y) * s,
+            z: 0.0,
+        };
+
+        Some((
+            first.position + (second.position - first.position) * t as f32,
+            euler_angles,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(x: f32) -> WorldCoordinate {
+        WorldCoordinate { x, y: 0.0, z: 0.0 }
+    }
+
+    fn looking(yaw: f32) -> Vector3 {
+        Vector3 {
+            x: 0.0,
+            y: yaw,
+            z: 0.0,
+        }
+    }
+
+    fn buffer() -> SnapshotBuffer {
+        SnapshotBuffer::new(InterpolationSettings::default())
+    }
+
+    fn sample_x(buffer: &mut SnapshotBuffer) -> f32 {
+        buffer.sample().unwrap().0.x
+    }
+
+    fn assert_near(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 0.01,
+            "{} is not {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn out_of_order_snapshots_are_sorted() {
+        let mut buffer = buffer();
+        buffer.push(1000, at(0.0), looking(0.0));
+        buffer.push(1200, at(20.0), looking(0.0));
+        buffer.push(1100, at(50.0), looking(0.0));
+
+        // the clock estimate is 1195ms ahead, so sampled 1095ms ahead with the delay
+        buffer.advance(0.055);
+        assert_near(sample_x(&mut buffer), 35.0);
+    }
+
+    #[test]
+    fn snapshots_older_than_the_front_are_dropped() {
+        let mut buffer = buffer();
+        buffer.push(1000, at(0.0), looking(0.0));
+        buffer.push(1100, at(10.0), looking(0.0));
+        buffer.push(900, at(999.0), looking(0.0));
+
+        // sampled before the first snapshot
+        assert_near(sample_x(&mut buffer), 0.0);
+    }
+
+    #[test]
+    fn duplicate_timestamps_keep_the_first_snapshot() {
+        let mut buffer = buffer();
+        buffer.push(1000, at(0.0), looking(0.0));
+        buffer.push(1100, at(10.0), looking(0.0));
+        buffer.push(1100, at(999.0), looking(0.0));
+
+        buffer.advance(0.1);
+        assert_near(sample_x(&mut buffer), 10.0);
+    }
+
+    #[test]
+    fn extrapolation_stops_after_max_extrapolation() {
+        let mut buffer = buffer();
+        buffer.push(1000, at(0.0), looking(0.0));
+        buffer.push(1100, at(10.0), looking(10.0));
+
+        // 50ms past the last snapshot, along the last velocity
+        buffer.advance(0.15);
+        assert_near(sample_x(&mut buffer), 15.0);
+
+        // then no further than 250ms past it, without turning the head any further
+        buffer.advance(1.0);
+        let (position, euler_angles) = buffer.sample().unwrap();
+        assert_near(position.x, 35.0);
+        assert_near(euler_angles.y, 10.0);
+    }
+
+    #[test]
+    fn yaw_goes_the_shortest_way_around() {
+        assert_near(angle_delta(350.0, 10.0), 20.0);
+        assert_near(angle_delta(10.0, 350.0), -20.0);
+        assert_near(angle_delta(-170.0, 170.0), -20.0);
+
+        let mut buffer = buffer();
+        buffer.push(1000, at(0.0), looking(350.0));
+        buffer.push(1100, at(0.0), looking(10.0));
+
+        buffer.advance(0.05);
+        let yaw = buffer.sample().unwrap().1.y;
+        assert_near(
+            yaw.rem_euclid(360.0).min(360.0 - yaw.rem_euclid(360.0)),
+            0.0,
+        );
+    }
+}
@@ -1,18 +1,31 @@
 use crate::game::{
-    entity::{Player, PlayerId},
-    MainPlayer,
+    entity::{InterpolationSettings, Player, PlayerId},
+    raycast, BlockEdits, Chat, ChunkStreamer, Login, MainPlayer, PlayerListEntry, Prediction,
+    ReplayEntry, ReplayPlayer, ReplayRecorder, WorldClock,
+};
+use crate::input::{Action, InputHandler, TextInputEvent};
+use crate::network::{
+    BlockAction, BlockCoordinate, ConnectionState, GameMessage, LoginRejection, NetworkHandler,
+    NetworkStats, PositionDecoder, RemoteInfo, MAX_MESSAGE_LENGTH,
 };
-use crate::input::InputHandler;
-use crate::network::{NetworkHandler, RemoteInfo};
 use crate::render::renderer::Renderer;
 
+use core::block::Block;
+use core::chunk::ChunkGridCoordinate;
 use core::events::{ClientEvent, ServerEvent};
 use core::world::{World, WorldCoordinate};
+use glutin::event::MouseButton;
 use std::collections::HashMap;
-use std::io;
-use std::time::Instant;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
-pub const NETWORK_UPDATE_TIMEOUT: u128 = 50;
+const NETWORK_UPDATE_TIMEOUT: u128 = 50;
+const MAX_INPUTS_PER_UPDATE: usize = 32;
+/// seconds skipped by the replay seek keys
+const REPLAY_SEEK_STEP: f64 = 5.0;
+/// how far the player can break and place blocks, in blocks
+const REACH: f32 = 6.0;
 
 #[derive(Debug, Clone)]
 pub enum GameType {
@@ -21,6 +34,10 @@ pub enum GameType {
     #[allow(dead_code)]
     Remote {
         info: RemoteInfo,
+        username: String,
+    },
+    Replay {
+        path: PathBuf,
     },
 }
 
@@ -28,80 +45,444 @@ pub struct Game {
     world: Option<World>,
     player: MainPlayer,
     players: HashMap<PlayerId, Player>,
-    renderer: Renderer,
+    position_decoders: HashMap<PlayerId, PositionDecoder>,
+    interpolation: InterpolationSettings,
+    /// `None` for games without a window, in tests
+    renderer: Option<Renderer>,
     network: Option<NetworkHandler>,
+    login: Option<Login>,
+    /// id the server gave the main player once logged in
+    player_id: Option<PlayerId>,
+    login_error: Option<LoginRejection>,
+    /// round trip time of the main player as seen by the server, in milliseconds
+    latency: Option<u16>,
+    show_player_list: bool,
+    prediction: Prediction,
+    block_edits: BlockEdits,
+    chat: Chat,
+    /// block placed with the right button, picked from the world with the middle one
+    selected_block: Block,
+    clock: WorldClock,
+    chunk_streamer: ChunkStreamer,
     last_network_update: Instant,
+    recorder: Option<ReplayRecorder>,
+    replay: Option<ReplayPlayer>,
+    /// the player asked to leave the server, the connection closing is expected
+    leaving: bool,
 }
 
 impl Game {
     pub fn new(game_type: GameType) -> io::Result<Self> {
+        Self::with_renderer(game_type, Some(Renderer::new(0, 0)))
+    }
+
+    #[cfg(test)]
+    fn headless(game_type: GameType) -> io::Result<Self> {
+        Self::with_renderer(game_type, None)
+    }
+
+    fn with_renderer(game_type: GameType, renderer: Option<Renderer>) -> io::Result<Self> {
         let player = MainPlayer::new(WorldCoordinate {
             x: 0.0,
             y: 70.0,
             z: 0.0,
         });
 
-        Ok(match game_type.clone() {
-            GameType::Local => Self {
-                world: Some(World::new()),
-                player,
-                players: HashMap::new(),
-                renderer: Renderer::new(0, 0),
-                network: None,
-                last_network_update: Instant::now(),
-            },
-            GameType::Remote { info } => Self {
-                world: None,
-                player,
-                players: HashMap::new(),
-                renderer: Renderer::new(0, 0),
-                network: (|| {
-                    let network = NetworkHandler::new(info).ok()?;
-                    network.send(ClientEvent::PlayerConnect);
-                    Some(network)
-                })(),
-                last_network_update: Instant::now(),
+        let mut game = Self {
+            world: None,
+            player,
+            players: HashMap::new(),
+            position_decoders: HashMap::new(),
+            interpolation: InterpolationSettings::default(),
+            renderer,
+            network: None,
+            login: None,
+            player_id: None,
+            login_error: None,
+            latency: None,
+            show_player_list: false,
+            prediction: Prediction::new(),
+            block_edits: BlockEdits::new(),
+            chat: Chat::new(),
+            selected_block: Block { id: 1 },
+            clock: WorldClock::new(),
+            chunk_streamer: ChunkStreamer::new(),
+            last_network_update: Instant::now(),
+            recorder: None,
+            replay: None,
+            leaving: false,
+        };
+
+        match game_type {
+            GameType::Local => game.world = Some(World::new()),
+            GameType::Remote { info, username } => {
+                game.login = Some(Login::new(username, &info));
+                game.network = match NetworkHandler::new(info) {
+                    Ok(network) => Some(network),
+                    Err(err) => {
+                        error!("<game> could not connect to the server: {}", err);
+                        None
+                    }
+                }
+            }
+            GameType::Replay { path } => game.replay = Some(ReplayPlayer::open(&path)?),
+        }
+
+        Ok(game)
+    }
+
+    /// records every message received from the server along with the player transforms
+    pub fn start_recording(&mut self, path: &Path) -> io::Result<()> {
+        if self.replay.is_some() {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "a replay can't be recorded",
+            ));
+        }
+
+        self.recorder = Some(ReplayRecorder::create(path)?);
+        Ok(())
+    }
+
+    /// state of the connection to the server, `None` for local games
+    pub fn connection_state(&self) -> Option<&ConnectionState> {
+        self.network.as_ref().map(|network| network.state())
+    }
+
+    /// leaves the server, the connection stays active until the server got the disconnect
+    pub fn disconnect(&mut self) {
+        if let Some(network) = self.network.as_mut() {
+            self.leaving = true;
+            network.disconnect();
+        }
+    }
+
+    /// reason the server refused the login, the game can't continue once set
+    pub fn login_error(&self) -> Option<LoginRejection> {
+        self.login_error
+    }
+
+    /// health of the connection to the server, `None` for local games
+    pub fn network_stats(&self) -> Option<NetworkStats> {
+        self.network.as_ref().map(|network| network.stats())
+    }
+
+    /// world tick and time of day, shared with the server in networked games
+    // TODO: remove this dead_code attribute when game logic depends on the time of day
+    #[allow(dead_code)]
+    pub fn clock(&self) -> &WorldClock {
+        &self.clock
+    }
+
+    // TODO: remove this dead_code attribute when we have a menu for this
+    #[allow(dead_code)]
+    pub fn set_interpolation_settings(&mut self, settings: InterpolationSettings) {
+        self.interpolation = settings;
+
+        for player in self.players.values_mut() {
+            player.set_interpolation_settings(settings);
+        }
+    }
+
+    /// breaks or places a block right away, remote games roll it back if the server refuses it
+    pub fn edit_block(&mut self, coords: BlockCoordinate, action: BlockAction) {
+        let world = match self.world.as_mut() {
+            Some(world) if self.replay.is_none() => world,
+            _ => return,
+        };
+
+        match self.network.as_mut() {
+            Some(network) => match self.block_edits.apply(world, coords, action) {
+                Some(edit) => network.edit_block(edit, coords, action),
+                None => return,
             },
-        })
+            None => {
+                if coords.set_block(world, action.block()).is_none() {
+                    return;
+                }
+            }
+        }
+
+        self.invalidate_chunks(coords.meshes());
     }
 
     pub fn resize(&mut self, width: usize, height: usize) {
-        self.renderer.resize(width, height);
+        if let Some(renderer) = self.renderer.as_mut() {
+            renderer.resize(width, height);
+        }
+
         self.player
             .camera
             .set_aspect_ratio(width as f32 / height as f32);
     }
 
     pub fn render(&self) {
+        let renderer = match self.renderer.as_ref() {
+            Some(renderer) => renderer,
+            None => return,
+        };
+
         let pals = self.players.values().collect::<Vec<&Player>>();
-        self.renderer.draw(&self.player.camera, &pals)
+        let player_list = if self.show_player_list {
+            Some(self.player_list())
+        } else {
+            None
+        };
+
+        renderer.draw(
+            &self.player.camera,
+            &pals,
+            &self.chat,
+            &self.clock,
+            player_list.as_deref(),
+        )
     }
 
-    pub fn update(&mut self, time_delta: f64, input_handler: &InputHandler) {
+    /// remeshes chunks after their blocks changed
+    fn invalidate_chunks(&mut self, chunks: Vec<ChunkGridCoordinate>) {
+        if let Some(renderer) = self.renderer.as_mut() {
+            renderer.invalidate_chunks(chunks);
+        }
+    }
+
+    /// name of the main player, only known once logged in
+    fn username(&self) -> &str {
+        match self.login.as_ref() {
+            Some(login) => &login.username,
+            None => "player",
+        }
+    }
+
+    /// the main player followed by everyone else in alphabetical order
+    fn player_list(&self) -> Vec<PlayerListEntry> {
+        let name = String::from(self.username());
+
+        let mut others: Vec<PlayerListEntry> = self
+            .players
+            .values()
+            .map(|player| PlayerListEntry {
+                name: String::from(player.name().unwrap_or("...")),
+                latency: player.latency(),
+            })
+            .collect();
+        others.sort_by(|a, b| a.name.cmp(&b.name));
+
+        // measured on our end when connected, replays only have what the server reported
+        let latency = self
+            .network
+            .as_ref()
+            .and_then(|network| network.stats().rtt)
+            .map(|rtt| rtt.round() as u16)
+            .or(self.latency);
+
+        let mut entries = vec![PlayerListEntry { name, latency }];
+        entries.append(&mut others);
+        entries
+    }
+
+    pub fn update(&mut self, time_delta: f64, input_handler: &mut InputHandler) {
         self.poll_network();
+        self.update_chat(input_handler);
+        // shown as long as the key is held
+        self.show_player_list = input_handler.is_action_pressed(Action::ShowPlayerList);
         self.update_world(time_delta, input_handler);
         self.update_network();
     }
 
+    /// local games generate their own world, other games receive it from a server
+    fn is_local(&self) -> bool {
+        self.network.is_none() && self.replay.is_none()
+    }
+
     fn update_world(&mut self, time_delta: f64, input_handler: &InputHandler) {
-        self.player.update(time_delta, &input_handler);
+        // replays run on their own clock, which can be paused, sped up or moved around
+        let time_delta = if self.replay.is_some() {
+            self.update_replay(time_delta, input_handler)
+        } else {
+            let input = self.player.update(time_delta, &input_handler);
+
+            if self.network.is_some() && !input.is_idle() {
+                self.prediction.record(input);
+            }
+
+            self.update_blocks(input_handler);
+            time_delta
+        };
+
+        self.clock.update(time_delta);
+
+        if let Some(recorder) = self.recorder.as_mut() {
+            recorder.record_transform(self.player.position(), self.player.camera.euler_angles());
+        }
 
         for (_, player) in self.players.iter_mut() {
-            player.update();
+            player.update(time_delta);
         }
 
+        let is_local = self.is_local();
         if let Some(world) = self.world.as_mut() {
-            world.load_around(vec![self.player.position()]);
-            self.renderer.update(&world, &input_handler);
+            // remote worlds are streamed from the server in `update_network`
+            if is_local {
+                world.load_around(vec![self.player.position()]);
+            }
+
+            if let Some(renderer) = self.renderer.as_mut() {
+                renderer.update(&world, &input_handler);
+            }
         }
     }
 
+    /// the left button breaks the block the player looks at, the right one places the selected
+    /// block against it and the middle one selects it
+    fn update_blocks(&mut self, input: &InputHandler) {
+        if input.is_typing() {
+            return;
+        }
+
+        let target = match self.world.as_ref() {
+            Some(world) => raycast(
+                world,
+                self.player.position(),
+                self.player.direction(),
+                REACH,
+            ),
+            None => None,
+        };
+
+        let target = match target {
+            Some(target) => target,
+            None => return,
+        };
+
+        if input.mouse_just_pressed(MouseButton::Left) {
+            self.edit_block(target.coords, BlockAction::Break);
+        } else if input.mouse_just_pressed(MouseButton::Right) {
+            self.edit_block(target.before, BlockAction::Place(self.selected_block));
+        } else if input.mouse_just_pressed(MouseButton::Middle) {
+            self.selected_block = target.block;
+        }
+    }
+
+    /// the open chat action starts typing, `Enter` sends the message and `Escape` closes it
+    fn update_chat(&mut self, input: &mut InputHandler) {
+        match input.take_text_event() {
+            Some(TextInputEvent::Submitted(text)) => self.send_chat(text),
+            Some(TextInputEvent::Cancelled) => (),
+            None => {
+                if self.replay.is_none() && input.action_just_pressed(Action::OpenChat) {
+                    input.start_text_input(MAX_MESSAGE_LENGTH);
+                }
+            }
+        }
+
+        self.chat.set_draft(input.typed_line().cloned());
+    }
+
+    fn send_chat(&mut self, text: String) {
+        let text = text.trim();
+        if text.is_empty() {
+            return;
+        }
+
+        if let Err(err) = self.chat.validate(text) {
+            self.chat.push(None, err.to_string());
+            return;
+        }
+
+        match self.network.as_mut() {
+            // the message comes back along with everyone else's once the server accepted it
+            Some(network) => network.send_chat(String::from(text)),
+            None => {
+                let name = String::from(self.username());
+                self.chat.push(Some(name), String::from(text));
+            }
+        }
+    }
+
+    /// applies the recorded entries due this frame and returns how far the replay moved forward
+    fn update_replay(&mut self, time_delta: f64, input: &InputHandler) -> f64 {
+        let replay = self.replay.as_mut().unwrap();
+        let start_time = replay.time();
+
+        if input.action_just_pressed(Action::ReplayPause) {
+            replay.toggle_pause();
+        }
+
+        if input.action_just_pressed(Action::ReplaySpeedUp) {
+            replay.set_speed(replay.speed() * 2.0);
+        }
+
+        if input.action_just_pressed(Action::ReplaySlowDown) {
+            replay.set_speed(replay.speed() / 2.0);
+        }
+
+        let mut rewind = false;
+        if input.action_just_pressed(Action::ReplaySeekBackward) {
+            rewind = replay.seek(replay.time() - REPLAY_SEEK_STEP);
+        }
+
+        if input.action_just_pressed(Action::ReplaySeekForward) {
+            rewind = replay.seek(replay.time() + REPLAY_SEEK_STEP) || rewind;
+        }
+
+        let entries = replay.advance(time_delta);
+        let elapsed = (replay.time() - start_time).max(0.0);
+
+        if rewind {
+            self.world = None;
+            self.players.clear();
+            self.position_decoders.clear();
+            self.block_edits.clear();
+            self.prediction = Prediction::new();
+        }
+
+        for entry in entries {
+            match entry {
+                ReplayEntry::Message(data) => match bincode::deserialize(&data) {
+                    Ok(message) => self.handle_message(message),
+                    Err(err) => error!("<replay> could not read message: {}", err),
+                },
+                ReplayEntry::Transform {
+                    position,
+                    euler_angles,
+                } => {
+                    self.player.set_position(position);
+                    self.player.camera.set_euler_angles(euler_angles);
+                }
+            }
+        }
+
+        elapsed
+    }
+
     fn update_network(&mut self) {
-        if let Some(network) = self.network.as_ref() {
+        if let Some(network) = self.network.as_mut() {
             if self.last_network_update.elapsed().as_millis() >= NETWORK_UPDATE_TIMEOUT {
-                network.send(ClientEvent::PlayerMove {
-                    position: self.player.position(),
-                });
+                // nothing is pending while the player stands still
+                network.send_inputs(self.prediction.unacknowledged(MAX_INPUTS_PER_UPDATE));
+                network.send_look(self.player.camera.euler_angles());
+
+                let moves: Vec<(PlayerId, u16)> = self
+                    .position_decoders
+                    .iter_mut()
+                    .filter_map(|(id, decoder)| {
+                        decoder
+                            .take_acknowledgement()
+                            .map(|sequence| (*id, sequence))
+                    })
+                    .collect();
+
+                if !moves.is_empty() {
+                    network.acknowledge_moves(moves);
+                }
+
+                if let Some(world) = self.world.as_mut() {
+                    let coords = self.chunk_streamer.update(world, self.player.position());
+
+                    if !coords.is_empty() {
+                        network.request_chunks(coords);
+                    }
+                }
 
                 self.last_network_update = Instant::now();
             }
@@ -113,43 +494,357 @@ impl Game {
             return;
         }
 
-        if let Ok(events) = self.network.as_ref().unwrap().process() {
-            for event in events {
-                match event {
-                    ServerEvent::PlayerConnected { id } => {
-                        self.players.insert(id, Player::new());
+        let network = self.network.as_mut().unwrap();
+        let result = network.process();
+
+        if let Some(state) = network.take_state_change() {
+            match state {
+                ConnectionState::Connected => {
+                    info!("<game> connected to the server");
+
+                    if let Some(login) = self.login.as_ref() {
+                        network.login(login.username.clone(), login.token());
                     }
-                    ServerEvent::PlayerDisconnected { id } => {
-                        self.players.remove(&id);
+                }
+                ConnectionState::Connecting | ConnectionState::Disconnecting => (),
+                ConnectionState::Disconnected if self.leaving => {
+                    info!("<game> disconnected from the server")
+                }
+                ConnectionState::Reconnecting { attempt } => {
+                    warn!(
+                        "<game> lost connection to the server, reconnecting (attempt {})",
+                        attempt
+                    );
+                    self.reset_session();
+                }
+                state => {
+                    error!("<game> lost connection to the server: {}", state);
+                    self.reset_session();
+                }
+            }
+        }
+
+        if let Ok(messages) = result {
+            for message in messages {
+                if let Some(recorder) = self.recorder.as_mut() {
+                    recorder.record_message(&message);
+                }
+
+                self.handle_message(message);
+            }
+        } else {
+            error!("<game> could not process network events");
+        }
+    }
+
+    /// forgets everything the server told us, the world is sent again after the next login
+    fn reset_session(&mut self) {
+        self.player_id = None;
+        self.latency = None;
+        self.players.clear();
+        self.position_decoders.clear();
+        self.prediction = Prediction::new();
+        self.block_edits.clear();
+        self.chunk_streamer.clear();
+        self.clock.reset();
+    }
+
+    fn handle_message(&mut self, message: GameMessage) {
+        match message {
+            GameMessage::Event(event) => self.handle_event(event),
+            GameMessage::LoginAccepted { id, token } => {
+                // also recorded in replays, where there is no one to log in
+                if let (Some(login), Some(network)) = (self.login.as_mut(), self.network.as_mut()) {
+                    info!("<game> logged in as {}", login.username);
+
+                    login.accepted(token);
+                    self.player_id = Some(id);
+                    network.send(ClientEvent::PlayerConnect);
+                }
+            }
+            GameMessage::LoginRejected { reason } => {
+                if let (Some(login), Some(_)) = (self.login.as_mut(), self.network.as_ref()) {
+                    error!("<game> the server refused the login: {}", reason);
+
+                    // the session is still valid when the login failed for another reason
+                    if reason == LoginRejection::InvalidToken {
+                        login.forget_token();
                     }
-                    ServerEvent::PlayerMoved { id, position } => {
-                        if let Some(player) = self.players.get_mut(&id) {
-                            player.set_position(position);
-                        }
+
+                    self.login_error = Some(reason);
+                    self.disconnect();
+                }
+            }
+            GameMessage::Chat { name, text } => self.chat.push(name, text),
+            GameMessage::PlayerName { id, name } => {
+                if let Some(player) = self.players.get_mut(&id) {
+                    info!("<game> {} is in the game", name);
+                    player.set_name(name);
+                }
+            }
+            GameMessage::PlayerLatencies { latencies } => {
+                for (id, latency) in latencies {
+                    if Some(id) == self.player_id {
+                        self.latency = Some(latency);
+                    } else if let Some(player) = self.players.get_mut(&id) {
+                        player.set_latency(latency);
+                    }
+                }
+            }
+            GameMessage::PlayerState { sequence, position } => {
+                if let Some(position) = self.prediction.reconcile(sequence, position.position()) {
+                    self.player.set_position(position);
+                }
+            }
+            GameMessage::PlayerMoved {
+                id,
+                sequence,
+                timestamp,
+                position,
+                rotation,
+            } => {
+                if let Some(player) = self.players.get_mut(&id) {
+                    let decoder = self
+                        .position_decoders
+                        .entry(id)
+                        .or_insert_with(PositionDecoder::new);
+
+                    if let Some(position) = decoder.decode(sequence, position) {
+                        player.push_snapshot(timestamp, position, rotation.euler_angles());
+                    }
+                }
+            }
+            GameMessage::ChunkData { chunk } => {
+                if let Some(world) = self.world.as_mut() {
+                    let coords = chunk.coords;
+
+                    if !self.chunk_streamer.received(coords, self.player.position()) {
+                        return;
+                    }
+
+                    let replaced = world.chunks.insert(coords, chunk).is_some();
+                    // edits the server did not confirm yet are missing from the chunk it sent
+                    let mut meshes = self.block_edits.reapply(world, coords);
+
+                    if replaced {
+                        meshes.push(coords);
+                    }
+
+                    if !meshes.is_empty() {
+                        self.invalidate_chunks(meshes);
+                    }
+                }
+            }
+            GameMessage::WorldTime { tick, day_time } => {
+                // half the round trip, replays have no latency to make up for
+                let latency = self
+                    .network
+                    .as_ref()
+                    .and_then(|network| network.stats().rtt)
+                    .map_or(Duration::from_secs(0), |rtt| {
+                        Duration::from_secs_f64(rtt / 2000.0)
+                    });
+
+                self.clock.synchronize(tick, day_time, latency);
+            }
+            GameMessage::BlockChanged { coords, block } => {
+                if let Some(world) = self.world.as_mut() {
+                    self.block_edits.changed(coords, block);
+
+                    if coords.set_block(world, block).is_some() {
+                        self.invalidate_chunks(coords.meshes());
                     }
-                    ServerEvent::ServerInfo { seed, player_ids } => {
-                        self.world = Some(World::from_seed(seed));
+                }
+            }
+            GameMessage::BlockEditResult { edit, accepted } => {
+                if accepted {
+                    self.block_edits.accepted(edit);
+                } else if let Some((coords, previous)) = self.block_edits.rejected(edit) {
+                    warn!("<game> the server rejected the block edit at {:?}", coords);
 
-                        for id in player_ids.iter() {
-                            self.players.insert(*id, Player::new());
+                    if let Some(world) = self.world.as_mut() {
+                        if coords.set_block(world, previous).is_some() {
+                            self.invalidate_chunks(coords.meshes());
                         }
                     }
-                };
+                }
             }
-        } else {
-            error!("<game> could not process network events");
         }
     }
 
-    fn send_event(&self, event: ClientEvent) {
-        if let Some(network) = self.network.as_ref() {
-            network.send(event);
-        }
+    fn handle_event(&mut self, event: ServerEvent) {
+        match event {
+            ServerEvent::PlayerConnected { id } if Some(id) != self.player_id => {
+                self.players.insert(id, Player::new(self.interpolation));
+            }
+            ServerEvent::PlayerConnected { .. } => (),
+            ServerEvent::PlayerDisconnected { id } => {
+                if let Some(name) = self.players.remove(&id).as_ref().and_then(Player::name) {
+                    info!("<game> {} left the game", name);
+                }
+
+                self.position_decoders.remove(&id);
+            }
+            ServerEvent::PlayerMoved { id, position } => {
+                if let Some(player) = self.players.get_mut(&id) {
+                    player.set_position(position);
+                }
+            }
+            ServerEvent::ServerInfo { player_ids, .. } => {
+                // chunks are streamed from the server, the seed is only used by local games
+                self.world = Some(World::new());
+                self.chunk_streamer.clear();
+                self.position_decoders.clear();
+                self.block_edits.clear();
+
+                for id in player_ids.iter() {
+                    if Some(*id) != self.player_id {
+                        self.players.insert(*id, Player::new(self.interpolation));
+                    }
+                }
+            }
+        };
     }
 }
 
 impl Drop for Game {
     fn drop(&mut self) {
-        self.send_event(ClientEvent::PlayerDisconnect);
+        if let Some(network) = self.network.as_mut() {
+            network.disconnect();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::Bindings;
+    use crate::network::MockServer;
+
+    use std::thread;
+
+    const TIME_DELTA: f64 = 1.0 / 60.0;
+
+    /// game connected to a mock server through an in-process pipe, no window needed
+    struct Session {
+        game: Game,
+        server: MockServer,
+        input: InputHandler,
+    }
+
+    impl Session {
+        /// logged in, with the world the server sent
+        fn start() -> Self {
+            let (server, info) = MockServer::memory(0);
+            let game = Game::headless(GameType::Remote {
+                info,
+                username: String::from("tester"),
+            })
+            .unwrap();
+
+            let mut session = Self {
+                game,
+                server,
+                input: InputHandler::new(Bindings::default()),
+            };
+
+            session.run_until(|game| game.player_id.is_some() && game.world.is_some());
+            session
+        }
+
+        /// runs the server and the game until `done` returns true
+        fn run_until<F: Fn(&Game) -> bool>(&mut self, done: F) {
+            let started = Instant::now();
+
+            while !done(&self.game) {
+                assert!(started.elapsed() < Duration::from_secs(5));
+
+                self.server.process();
+                self.game.update(TIME_DELTA, &mut self.input);
+                self.input.clear();
+                thread::sleep(Duration::from_millis(1));
+            }
+        }
+    }
+
+    #[test]
+    fn server_info_creates_the_world_and_the_other_players() {
+        let mut session = Session::start();
+        assert!(session.game.players.is_empty());
+
+        let id = session.game.player_id.unwrap();
+        session.server.send_event(ServerEvent::ServerInfo {
+            seed: 0,
+            player_ids: vec![3, 4, id],
+        });
+        session.run_until(|game| game.players.len() == 2);
+
+        assert!(session.game.world.is_some());
+        assert!(session.game.players.contains_key(&3));
+        assert!(session.game.players.contains_key(&4));
+    }
+
+    #[test]
+    fn player_connected_adds_the_player() {
+        let mut session = Session::start();
+
+        session.server.connect_player(3, String::from("three"));
+        session.run_until(|game| game.players.get(&3).and_then(Player::name) == Some("three"));
+
+        assert_eq!(session.game.players.len(), 1);
+    }
+
+    #[test]
+    fn player_moved_moves_the_player() {
+        let mut session = Session::start();
+        let position = WorldCoordinate {
+            x: 12.0,
+            y: 64.0,
+            z: -3.0,
+        };
+
+        session.server.connect_player(3, String::from("three"));
+        session.run_until(|game| game.players.contains_key(&3));
+        session
+            .server
+            .send_event(ServerEvent::PlayerMoved { id: 3, position });
+        session.run_until(|game| {
+            let moved = game.players[&3].position();
+            moved.x == position.x && moved.y == position.y && moved.z == position.z
+        });
+    }
+
+    #[test]
+    fn player_disconnected_removes_the_player() {
+        let mut session = Session::start();
+
+        session.server.connect_player(3, String::from("three"));
+        session.server.connect_player(4, String::from("four"));
+        session.run_until(|game| game.players.len() == 2);
+        session.server.disconnect_player(3);
+        session.run_until(|game| !game.players.contains_key(&3));
+
+        assert!(session.game.players.contains_key(&4));
+    }
+
+    #[test]
+    fn only_a_rejected_token_is_forgotten() {
+        let mut session = Session::start();
+        let token = |game: &Game| game.login.as_ref().unwrap().token();
+        assert!(token(&session.game).is_some());
+
+        session.game.handle_message(GameMessage::LoginRejected {
+            reason: LoginRejection::AlreadyPlaying,
+        });
+        assert!(token(&session.game).is_some());
+        assert_eq!(
+            session.game.login_error(),
+            Some(LoginRejection::AlreadyPlaying)
+        );
+
+        session.game.handle_message(GameMessage::LoginRejected {
+            reason: LoginRejection::InvalidToken,
+        });
+        assert!(token(&session.game).is_none());
     }
 }
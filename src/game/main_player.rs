@@ -1,11 +1,10 @@
-use crate::input::InputHandler;
+use crate::input::{Axis, InputHandler};
+use crate::network::PlayerInput;
 use crate::render::camera::{Camera, PerspectiveCamera};
 
 use core::world::WorldCoordinate;
-use glutin::event::VirtualKeyCode;
 use math::vector::Vector3;
 
-const SPEED: f64 = 20.0;
 const SENSITIVITY: f32 = 0.2;
 
 pub struct MainPlayer {
@@ -22,7 +21,7 @@ impl MainPlayer {
         p
     }
 
-    fn set_position(&mut self, position: WorldCoordinate) {
+    pub fn set_position(&mut self, position: WorldCoordinate) {
         self.camera.set_position(position);
     }
 
@@ -30,13 +29,32 @@ impl MainPlayer {
         self.camera.position()
     }
 
-    pub fn update(&mut self, time_delta: f64, input: &InputHandler) {
-        let cursor_delta = input.get_cursor_delta();
-        let camera_delta = Vector3 {
-            x: cursor_delta.y as f32,
-            y: cursor_delta.x as f32,
-            z: 0.0,
-        } * SENSITIVITY;
+    /// unit vector along which the camera looks, positive pitch looks down
+    pub fn direction(&self) -> Vector3 {
+        let euler_angles = self.camera.euler_angles();
+        let pitch = euler_angles.x.to_radians();
+        let yaw = euler_angles.y.to_radians();
+
+        Vector3 {
+            x: yaw.sin() * pitch.cos(),
+            y: -pitch.sin(),
+            z: yaw.cos() * pitch.cos(),
+        }
+    }
+
+    /// rotates the camera, moves the player and returns the movement that was applied
+    pub fn update(&mut self, time_delta: f64, input: &InputHandler) -> PlayerInput {
+        // the camera stays put while typing in the chat
+        let camera_delta = if input.is_typing() {
+            Vector3::zero()
+        } else {
+            let cursor_delta = input.get_cursor_delta();
+            Vector3 {
+                x: cursor_delta.y as f32,
+                y: cursor_delta.x as f32,
+                z: 0.0,
+            } * SENSITIVITY
+        };
         let mut camera_angles = self.camera.euler_angles() + camera_delta;
 
         if camera_angles.x > 90.0 {
@@ -49,43 +67,100 @@ impl MainPlayer {
 
         self.camera.set_euler_angles(camera_angles);
 
-        let mut xaxis = 0.0;
-        let mut yaxis = 0.0;
-        let mut zaxis = 0.0;
-
-        if input.is_key_pressed(VirtualKeyCode::W) {
-            zaxis += 1.0;
+        let movement = PlayerInput {
+            sequence: 0,
+            x: input.axis(Axis::MoveX),
+            y: input.axis(Axis::MoveY),
+            z: input.axis(Axis::MoveZ),
+            yaw: self.camera.euler_angles().y,
+            time_delta: time_delta as f32,
         }
+        .quantized();
 
-        if input.is_key_pressed(VirtualKeyCode::S) {
-            zaxis -= 1.0;
-        }
+        self.set_position(movement.apply(self.camera.position()));
+        movement
+    }
+}
 
-        if input.is_key_pressed(VirtualKeyCode::A) {
-            xaxis -= 1.0;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::{
+        Bindings, CursorDelta, InputFrame, InputPlayback, InputRecorder, PLAYBACK_TIME_DELTA,
+    };
+
+    use glutin::event::VirtualKeyCode;
+    use std::env;
+    use std::fs;
+
+    fn frame(pressed: Vec<VirtualKeyCode>, x: f64, y: f64) -> InputFrame {
+        InputFrame {
+            pressed,
+            cursor_delta: CursorDelta { x, y },
+            ..Default::default()
         }
+    }
 
-        if input.is_key_pressed(VirtualKeyCode::D) {
-            xaxis += 1.0;
-        }
+    fn assert_near(actual: Vector3, expected: (f32, f32, f32)) {
+        let close = |a: f32, b: f32| (a - b).abs() < 0.05;
+        assert!(
+            close(actual.x, expected.0)
+                && close(actual.y, expected.1)
+                && close(actual.z, expected.2),
+            "{:?} is not {:?}",
+            actual,
+            expected
+        );
+    }
 
-        if input.is_key_pressed(VirtualKeyCode::Space) {
-            yaxis += 1.0;
-        }
+    #[test]
+    fn playback_moves_and_turns_the_player() {
+        let bindings_path = env::temp_dir().join("playback-bindings.json");
+        let recording_path = env::temp_dir().join("playback.input");
+
+        // recorded with forward on another key than the default one
+        fs::write(&bindings_path, r#"{ "MoveForward": ["Up"] }"#).unwrap();
+        let bindings = Bindings::load(&bindings_path);
 
-        if input.is_key_pressed(VirtualKeyCode::LShift) {
-            yaxis -= 1.0;
+        let mut recorder = InputRecorder::create(&recording_path, &bindings).unwrap();
+        for _ in 0..30 {
+            recorder.record(&frame(vec![VirtualKeyCode::Up], 0.0, 0.0));
         }
+        for _ in 0..10 {
+            recorder.record(&frame(Vec::new(), 5.0, -2.0));
+        }
+        drop(recorder);
 
-        let angle = self.camera.euler_angles().y.to_radians();
+        let mut playback = InputPlayback::open(&recording_path).unwrap();
+        fs::remove_file(&bindings_path).unwrap();
+        fs::remove_file(&recording_path).unwrap();
 
-        let mut delta = Vector3 {
-            x: xaxis * angle.cos() + zaxis * angle.sin(),
-            y: yaxis,
-            z: -xaxis * angle.sin() + zaxis * angle.cos(),
-        };
-        delta = delta * (SPEED * time_delta) as f32;
+        let mut input = InputHandler::new(Bindings::default());
+        *input.bindings_mut() = playback.bindings().clone();
+
+        let mut player = MainPlayer::new(WorldCoordinate {
+            x: 0.0,
+            y: 70.0,
+            z: 0.0,
+        });
+
+        let mut frames = 0;
+        while let Some(frame) = playback.next_frame() {
+            input.set_frame(&frame);
+            input.update(PLAYBACK_TIME_DELTA);
+            player.update(PLAYBACK_TIME_DELTA, &input);
+            input.clear();
+
+            frames += 1;
+            if frames == 30 {
+                // 20 blocks per second for half a second
+                assert_near(player.position(), (0.0, 70.0, 10.0));
+                assert_near(player.camera.euler_angles(), (0.0, 0.0, 0.0));
+            }
+        }
 
-        self.set_position(self.camera.position() + delta);
+        assert_eq!(frames, 40);
+        assert_near(player.position(), (0.0, 70.0, 10.0));
+        assert_near(player.camera.euler_angles(), (-4.0, 10.0, 0.0));
     }
 }
@@ -1,8 +1,26 @@
+mod block_edits;
+mod chat;
+mod chunk_streamer;
 pub mod entity;
 mod game;
 mod main_player;
+mod player_list;
+mod prediction;
+mod raycast;
+mod replay;
+mod session;
 mod texture;
+mod world_clock;
 
-pub use self::game::{Game, GameType, NETWORK_UPDATE_TIMEOUT};
+pub use self::block_edits::BlockEdits;
+pub use self::chat::Chat;
+pub use self::chunk_streamer::ChunkStreamer;
+pub use self::game::{Game, GameType};
 pub use self::main_player::MainPlayer;
+pub use self::player_list::{ConnectionQuality, PlayerListEntry};
+pub use self::prediction::Prediction;
+pub use self::raycast::raycast;
+pub use self::replay::{ReplayEntry, ReplayPlayer, ReplayRecorder};
+pub use self::session::Login;
 pub use self::texture::TextureDatabase;
+pub use self::world_clock::WorldClock;
@@ -0,0 +1,50 @@
+use std::fmt;
+
+/// latency in milliseconds under which a connection is considered good
+const GOOD_LATENCY: u16 = 100;
+/// latency in milliseconds under which a connection is considered fair
+const FAIR_LATENCY: u16 = 250;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ConnectionQuality {
+    Good,
+    Fair,
+    Poor,
+    /// the server did not report the latency of this player yet
+    Unknown,
+}
+
+impl ConnectionQuality {
+    pub fn from_latency(latency: Option<u16>) -> Self {
+        match latency {
+            Some(latency) if latency < GOOD_LATENCY => ConnectionQuality::Good,
+            Some(latency) if latency < FAIR_LATENCY => ConnectionQuality::Fair,
+            Some(_) => ConnectionQuality::Poor,
+            None => ConnectionQuality::Unknown,
+        }
+    }
+}
+
+impl fmt::Display for ConnectionQuality {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConnectionQuality::Good => write!(f, "good"),
+            ConnectionQuality::Fair => write!(f, "fair"),
+            ConnectionQuality::Poor => write!(f, "poor"),
+            ConnectionQuality::Unknown => write!(f, "-"),
+        }
+    }
+}
+
+/// A row of the player list overlay.
+pub struct PlayerListEntry {
+    pub name: String,
+    /// round trip time between the player and the server in milliseconds
+    pub latency: Option<u16>,
+}
+
+impl PlayerListEntry {
+    pub fn quality(&self) -> ConnectionQuality {
+        ConnectionQuality::from_latency(self.latency)
+    }
+}
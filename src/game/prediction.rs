@@ -0,0 +1,177 @@
+use crate::network::PlayerInput;
+
+use core::world::WorldCoordinate;
+use std::collections::VecDeque;
+
+/// inputs older than this are forgotten even if the server never acknowledged them
+const MAX_PENDING_INPUTS: usize = 1024;
+
+/// Keeps the inputs the server did not process yet so they can be replayed on top of an
+/// authoritative position.
+pub struct Prediction {
+    next_sequence: u32,
+    pending: VecDeque<PlayerInput>,
+    last_acknowledged: Option<u32>,
+}
+
+impl Prediction {
+    pub fn new() -> Self {
+        Self {
+            next_sequence: 0,
+            pending: VecDeque::new(),
+            last_acknowledged: None,
+        }
+    }
+
+    /// assigns a sequence number to an input and keeps it until the server acknowledges it
+    pub fn record(&mut self, mut input: PlayerInput) -> PlayerInput {
+        input.sequence = self.next_sequence;
+        self.next_sequence += 1;
+
+        if self.pending.len() >= MAX_PENDING_INPUTS {
+            self.pending.pop_front();
+        }
+
+        self.pending.push_back(input);
+        input
+    }
+
+    /// most recent inputs not yet acknowledged by the server, oldest first
+    pub fn unacknowledged(&self, max: usize) -> Vec<PlayerInput> {
+        let skip = self.pending.len().saturating_sub(max);
+        self.pending.iter().skip(skip).copied().collect()
+    }
+
+    /// replays the pending inputs on top of the position the server computed after `sequence`,
+    /// returns `None` if the server state is older than one already applied
+    pub fn reconcile(
+        &mut self,
+        sequence: u32,
+        position: WorldCoordinate,
+    ) -> Option<WorldCoordinate> {
+        if self
+            .last_acknowledged
+            .map_or(false, |last| sequence <= last)
+        {
+            return None;
+        }
+
+        self.last_acknowledged = Some(sequence);
+
+        while self
+            .pending
+            .front()
+            .map_or(false, |input| input.sequence <= sequence)
+        {
+            self.pending.pop_front();
+        }
+
+        Some(
+            self.pending
+                .iter()
+                .fold(position, |position, input| input.apply(position)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ORIGIN: WorldCoordinate = WorldCoordinate {
+        x: 0.0,
+        y: 0.0,
+        z: 0.0,
+    };
+
+    /// a tenth of a second forward, 2 blocks
+    fn forward() -> PlayerInput {
+        PlayerInput {
+            z: 1.0,
+            time_delta: 0.1,
+            ..Default::default()
+        }
+    }
+
+    fn prediction(inputs: usize) -> Prediction {
+        let mut prediction = Prediction::new();
+        for _ in 0..inputs {
+            prediction.record(forward());
+        }
+
+        prediction
+    }
+
+    #[test]
+    fn inputs_are_numbered_in_order() {
+        let mut prediction = Prediction::new();
+
+        assert_eq!(prediction.record(forward()).sequence, 0);
+        assert_eq!(prediction.record(forward()).sequence, 1);
+
+        let sequences: Vec<u32> = prediction
+            .unacknowledged(1)
+            .iter()
+            .map(|input| input.sequence)
+            .collect();
+        assert_eq!(sequences, vec![1]);
+    }
+
+    #[test]
+    fn acknowledged_inputs_are_dropped() {
+        let mut prediction = prediction(5);
+
+        prediction.reconcile(2, ORIGIN).unwrap();
+
+        let sequences: Vec<u32> = prediction
+            .unacknowledged(10)
+            .iter()
+            .map(|input| input.sequence)
+            .collect();
+        assert_eq!(sequences, vec![3, 4]);
+    }
+
+    #[test]
+    fn pending_inputs_are_replayed_on_the_server_position() {
+        let mut prediction = prediction(5);
+        let server = WorldCoordinate {
+            x: 10.0,
+            y: 5.0,
+            z: 1.0,
+        };
+
+        let position = prediction.reconcile(2, server).unwrap();
+
+        assert_eq!(position.x, 10.0);
+        assert_eq!(position.y, 5.0);
+        assert!((position.z - 5.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn everything_acknowledged_keeps_the_server_position() {
+        let mut prediction = prediction(3);
+        let server = WorldCoordinate {
+            x: 1.0,
+            y: 2.0,
+            z: 3.0,
+        };
+
+        let position = prediction.reconcile(2, server).unwrap();
+
+        assert_eq!((position.x, position.y, position.z), (1.0, 2.0, 3.0));
+        assert!(prediction.unacknowledged(10).is_empty());
+    }
+
+    #[test]
+    fn stale_and_duplicate_acks_are_ignored() {
+        let mut prediction = prediction(5);
+
+        assert!(prediction.reconcile(3, ORIGIN).is_some());
+        assert!(prediction.reconcile(3, ORIGIN).is_none());
+        assert!(prediction.reconcile(1, ORIGIN).is_none());
+        assert_eq!(prediction.unacknowledged(10).len(), 1);
+
+        assert!(prediction.reconcile(4, ORIGIN).is_some());
+        assert!(prediction.unacknowledged(10).is_empty());
+    }
+}
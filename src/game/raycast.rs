@@ -0,0 +1,189 @@
+use crate::network::{BlockCoordinate, AIR};
+
+use core::block::Block;
+use core::world::{World, WorldCoordinate};
+use math::vector::Vector3;
+
+/// Solid block hit by a ray, along with the block the ray came from, where a block placed
+/// against the face that was hit goes.
+#[derive(Debug, Copy, Clone)]
+pub struct BlockTarget {
+    pub coords: BlockCoordinate,
+    pub block: Block,
+    pub before: BlockCoordinate,
+}
+
+/// walks the blocks crossed by the ray until one is not air, up to `reach` blocks away. The ray
+/// stops at chunks that are not loaded.
+pub fn raycast(
+    world: &World,
+    origin: WorldCoordinate,
+    direction: Vector3,
+    reach: f32,
+) -> Option<BlockTarget> {
+    let start = BlockCoordinate::from_position(origin);
+    let origin = [origin.x, origin.y, origin.z];
+    let direction = [direction.x, direction.y, direction.z];
+    let mut block = [start.x, start.y, start.z];
+
+    // distance along the ray to the next block boundary on each axis and between boundaries
+    let mut step = [0; 3];
+    let mut next = [f32::INFINITY; 3];
+    let mut delta = [f32::INFINITY; 3];
+
+    for axis in 0..3 {
+        if direction[axis] > 0.0 {
+            step[axis] = 1;
+            next[axis] = (block[axis] as f32 + 1.0 - origin[axis]) / direction[axis];
+            delta[axis] = 1.0 / direction[axis];
+        } else if direction[axis] < 0.0 {
+            step[axis] = -1;
+            next[axis] = (origin[axis] - block[axis] as f32) / -direction[axis];
+            delta[axis] = -1.0 / direction[axis];
+        }
+    }
+
+    loop {
+        let axis = (1..3).fold(0, |closest, axis| {
+            if next[axis] < next[closest] {
+                axis
+            } else {
+                closest
+            }
+        });
+
+        if next[axis] > reach {
+            return None;
+        }
+
+        let before = BlockCoordinate {
+            x: block[0],
+            y: block[1],
+            z: block[2],
+        };
+
+        block[axis] += step[axis];
+        next[axis] += delta[axis];
+
+        let coords = BlockCoordinate {
+            x: block[0],
+            y: block[1],
+            z: block[2],
+        };
+
+        match coords.block(world) {
+            Some(found) if found.id != AIR.id => {
+                return Some(BlockTarget {
+                    coords,
+                    block: found,
+                    before,
+                })
+            }
+            Some(_) => (),
+            None => return None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STONE: Block = Block { id: 1 };
+    const HEIGHT: i64 = 200;
+
+    /// a loaded, empty area around the origin with a single block of stone
+    fn world(stone: BlockCoordinate) -> World {
+        let mut world = World::new();
+        world.load_around(vec![WorldCoordinate {
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+        }]);
+
+        for x in -8..8 {
+            for y in HEIGHT - 8..HEIGHT + 8 {
+                BlockCoordinate { x, y, z: 0 }.set_block(&mut world, AIR);
+            }
+        }
+        stone.set_block(&mut world, STONE);
+
+        world
+    }
+
+    fn block(x: i64, y: i64) -> BlockCoordinate {
+        BlockCoordinate { x, y, z: 0 }
+    }
+
+    fn position(x: f32, y: f32) -> WorldCoordinate {
+        WorldCoordinate { x, y, z: 0.5 }
+    }
+
+    fn direction(x: f32, y: f32) -> Vector3 {
+        let length = (x * x + y * y).sqrt();
+        Vector3 {
+            x: x / length,
+            y: y / length,
+            z: 0.0,
+        }
+    }
+
+    #[test]
+    fn the_face_hit_from_the_side_places_blocks_beside() {
+        let world = world(block(3, HEIGHT));
+        let origin = position(0.5, HEIGHT as f32 + 0.5);
+
+        let target = raycast(&world, origin, direction(1.0, 0.0), 5.0).unwrap();
+        assert_eq!(target.coords, block(3, HEIGHT));
+        assert_eq!(target.before, block(2, HEIGHT));
+        assert_eq!(target.block.id, STONE.id);
+
+        let origin = position(6.5, HEIGHT as f32 + 0.5);
+        let target = raycast(&world, origin, direction(-1.0, 0.0), 5.0).unwrap();
+        assert_eq!(target.before, block(4, HEIGHT));
+    }
+
+    #[test]
+    fn the_face_hit_from_above_places_blocks_on_top() {
+        let world = world(block(3, HEIGHT));
+        let origin = position(3.5, HEIGHT as f32 + 3.5);
+
+        let target = raycast(&world, origin, direction(0.0, -1.0), 5.0).unwrap();
+        assert_eq!(target.coords, block(3, HEIGHT));
+        assert_eq!(target.before, block(3, HEIGHT + 1));
+    }
+
+    #[test]
+    fn slanted_rays_enter_through_the_first_face_crossed() {
+        let world = world(block(3, HEIGHT));
+
+        // goes down into the row of the stone before reaching it
+        let origin = position(0.5, HEIGHT as f32 + 1.2);
+        let target = raycast(&world, origin, direction(1.0, -0.2), 5.0).unwrap();
+        assert_eq!(target.before, block(2, HEIGHT));
+
+        // still above it when crossing its column
+        let origin = position(2.5, HEIGHT as f32 + 1.9);
+        let target = raycast(&world, origin, direction(1.0, -1.0), 5.0).unwrap();
+        assert_eq!(target.before, block(3, HEIGHT + 1));
+    }
+
+    #[test]
+    fn blocks_out_of_reach_are_not_hit() {
+        let world = world(block(3, HEIGHT));
+        let origin = position(0.5, HEIGHT as f32 + 0.5);
+
+        assert!(raycast(&world, origin, direction(1.0, 0.0), 2.0).is_none());
+        assert!(raycast(&world, origin, direction(-1.0, 0.0), 5.0).is_none());
+    }
+
+    #[test]
+    fn rays_stop_at_unloaded_chunks() {
+        let mut world = world(block(3, HEIGHT));
+        let origin = position(0.5, HEIGHT as f32 + 0.5);
+
+        world.chunks.remove(&block(-1, HEIGHT).chunk());
+        assert!(raycast(&world, origin, direction(-1.0, 0.0), 1000.0).is_none());
+        assert!(raycast(&world, origin, direction(1.0, 0.0), 1000.0).is_some());
+    }
+}
@@ -0,0 +1,320 @@
+use crate::network::{GameMessage, PROTOCOL_VERSION};
+
+use core::world::WorldCoordinate;
+use math::vector::Vector3;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, ErrorKind, Write};
+use std::path::Path;
+use std::time::Instant;
+
+const MIN_SPEED: f64 = 0.125;
+const MAX_SPEED: f64 = 8.0;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ReplayEntry {
+    /// bincode encoded `GameMessage` as received from the server
+    Message(Vec<u8>),
+    Transform {
+        position: WorldCoordinate,
+        euler_angles: Vector3,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ReplayFrame {
+    /// seconds since the start of the recording
+    time: f64,
+    entry: ReplayEntry,
+}
+
+fn invalid_data(err: bincode::Error) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, err)
+}
+
+pub struct ReplayRecorder {
+    writer: BufWriter<File>,
+    started: Instant,
+}
+
+impl ReplayRecorder {
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        bincode::serialize_into(&mut writer, &PROTOCOL_VERSION).map_err(invalid_data)?;
+
+        Ok(Self {
+            writer,
+            started: Instant::now(),
+        })
+    }
+
+    pub fn record_message(&mut self, message: &GameMessage) {
+        match bincode::serialize(message) {
+            Ok(data) => self.record(ReplayEntry::Message(data)),
+            Err(err) => error!("<replay> could not serialize message: {}", err),
+        }
+    }
+
+    pub fn record_transform(&mut self, position: WorldCoordinate, euler_angles: Vector3) {
+        self.record(ReplayEntry::Transform {
+            position,
+            euler_angles,
+        });
+    }
+
+    fn record(&mut self, entry: ReplayEntry) {
+        let frame = ReplayFrame {
+            time: self.started.elapsed().as_secs_f64(),
+            entry,
+        };
+
+        if let Err(err) = bincode::serialize_into(&mut self.writer, &frame) {
+            error!("<replay> could not write frame: {}", err);
+        }
+    }
+}
+
+impl Drop for ReplayRecorder {
+    fn drop(&mut self) {
+        let _ = self.writer.flush();
+    }
+}
+
+/// Plays a recording back. Seeking backward replays every frame from the start so the
+/// game state is rebuilt exactly like it was at that time.
+pub struct ReplayPlayer {
+    frames: Vec<ReplayFrame>,
+    cursor: usize,
+    time: f64,
+    speed: f64,
+    paused: bool,
+}
+
+impl ReplayPlayer {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let mut reader = BufReader::new(File::open(path)?);
+
+        let version: u32 = bincode::deserialize_from(&mut reader).map_err(invalid_data)?;
+        if version != PROTOCOL_VERSION {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "recording uses protocol version {} but this client speaks version {}",
+                    version, PROTOCOL_VERSION
+                ),
+            ));
+        }
+
+        let mut frames = Vec::new();
+        loop {
+            match bincode::deserialize_from(&mut reader) {
+                Ok(frame) => frames.push(frame),
+                Err(err) => match *err {
+                    bincode::ErrorKind::Io(ref err) if err.kind() == ErrorKind::UnexpectedEof => {
+                        break
+                    }
+                    _ => return Err(invalid_data(err)),
+                },
+            }
+        }
+
+        Ok(Self {
+            frames,
+            cursor: 0,
+            time: 0.0,
+            speed: 1.0,
+            paused: false,
+        })
+    }
+
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    pub fn duration(&self) -> f64 {
+        self.frames.last().map_or(0.0, |frame| frame.time)
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+    }
+
+    pub fn set_speed(&mut self, speed: f64) {
+        self.speed = speed.max(MIN_SPEED).min(MAX_SPEED);
+    }
+
+    pub fn speed(&self) -> f64 {
+        self.speed
+    }
+
+    /// moves the playhead, returns true if it went backward and the game state must be reset
+    pub fn seek(&mut self, time: f64) -> bool {
+        let time = time.max(0.0).min(self.duration());
+        let rewind = time < self.time;
+
+        if rewind {
+            self.cursor = 0;
+        }
+
+        self.time = time;
+        rewind
+    }
+
+    /// entries to apply this frame, in the order they were recorded
+    pub fn advance(&mut self, time_delta: f64) -> Vec<ReplayEntry> {
+        if !self.paused {
+            self.time = (self.time + time_delta * self.speed).min(self.duration());
+        }
+
+        let mut entries = Vec::new();
+        while let Some(frame) = self.frames.get(self.cursor) {
+            if frame.time > self.time {
+                break;
+            }
+
+            entries.push(frame.entry.clone());
+            self.cursor += 1;
+        }
+
+        entries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::env;
+    use std::fs;
+    use std::time::Duration;
+
+    fn chat(text: &str) -> GameMessage {
+        GameMessage::Chat {
+            name: None,
+            text: text.to_string(),
+        }
+    }
+
+    /// records a chat message at 1, 2 and 3 seconds and a transform at 4 seconds, then loads
+    /// the recording back
+    fn record(name: &str) -> ReplayPlayer {
+        let path = env::temp_dir().join(format!("replay-{}.bin", name));
+
+        {
+            let mut recorder = ReplayRecorder::create(&path).unwrap();
+            for (second, text) in ["one", "two", "three"].iter().enumerate() {
+                recorder.started = Instant::now() - Duration::from_secs(second as u64 + 1);
+                recorder.record_message(&chat(text));
+            }
+
+            recorder.started = Instant::now() - Duration::from_secs(4);
+            recorder.record_transform(
+                WorldCoordinate {
+                    x: 1.0,
+                    y: 2.0,
+                    z: 3.0,
+                },
+                Vector3 {
+                    x: 0.0,
+                    y: 90.0,
+                    z: 0.0,
+                },
+            );
+        }
+
+        let player = ReplayPlayer::open(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        player
+    }
+
+    fn texts(entries: Vec<ReplayEntry>) -> Vec<String> {
+        entries
+            .into_iter()
+            .filter_map(|entry| match entry {
+                ReplayEntry::Message(data) => match bincode::deserialize(&data).unwrap() {
+                    GameMessage::Chat { text, .. } => Some(text),
+                    _ => None,
+                },
+                ReplayEntry::Transform { .. } => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn recordings_are_played_back_in_order() {
+        let mut player = record("order");
+        assert!(player.duration() >= 4.0 && player.duration() < 4.5);
+
+        assert!(player.advance(0.5).is_empty());
+        assert_eq!(texts(player.advance(2.0)), vec!["one", "two"]);
+        assert_eq!(texts(player.advance(1.0)), vec!["three"]);
+
+        match player.advance(10.0).as_slice() {
+            [ReplayEntry::Transform { position, .. }] => assert_eq!(position.y, 2.0),
+            entries => panic!("unexpected {:?}", entries),
+        }
+
+        assert_eq!(player.time(), player.duration());
+        assert!(player.advance(1.0).is_empty());
+    }
+
+    #[test]
+    fn rewinding_plays_every_entry_again() {
+        let mut player = record("rewind");
+        player.advance(3.5);
+
+        assert!(!player.seek(3.8));
+        assert!(player.advance(0.0).is_empty());
+
+        assert!(player.seek(1.5));
+        assert_eq!(player.time(), 1.5);
+        assert_eq!(texts(player.advance(0.0)), vec!["one"]);
+
+        assert!(!player.seek(100.0));
+        assert_eq!(player.time(), player.duration());
+        assert!(player.seek(-1.0));
+        assert_eq!(player.time(), 0.0);
+    }
+
+    #[test]
+    fn nothing_is_played_while_paused() {
+        let mut player = record("pause");
+
+        player.toggle_pause();
+        assert!(player.advance(10.0).is_empty());
+        assert_eq!(player.time(), 0.0);
+
+        // seeking still works, to step through a paused replay
+        player.seek(1.5);
+        assert_eq!(texts(player.advance(10.0)), vec!["one"]);
+
+        player.toggle_pause();
+        assert_eq!(texts(player.advance(1.0)), vec!["two"]);
+    }
+
+    #[test]
+    fn the_speed_is_clamped() {
+        let mut player = record("speed");
+
+        player.set_speed(2.0);
+        assert_eq!(texts(player.advance(1.25)), vec!["one", "two"]);
+        assert_eq!(player.time(), 2.5);
+
+        player.set_speed(100.0);
+        assert_eq!(player.speed(), MAX_SPEED);
+        player.set_speed(0.0);
+        assert_eq!(player.speed(), MIN_SPEED);
+    }
+
+    #[test]
+    fn recordings_of_another_protocol_version_are_refused() {
+        let path = env::temp_dir().join("replay-version.bin");
+        fs::write(&path, bincode::serialize(&(PROTOCOL_VERSION + 1)).unwrap()).unwrap();
+
+        let result = ReplayPlayer::open(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(result.err().unwrap().kind(), ErrorKind::InvalidData);
+    }
+}
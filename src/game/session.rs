@@ -0,0 +1,92 @@
+use crate::network::RemoteInfo;
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// where session tokens are kept, relative to the working directory
+const SESSIONS_PATH: &str = "sessions.json";
+
+/// Session tokens handed out by servers, saved to a JSON file so logins survive restarts.
+pub struct SessionStore {
+    path: PathBuf,
+    /// token by `username@server`
+    tokens: HashMap<String, String>,
+}
+
+impl SessionStore {
+    /// starts empty if the file does not exist or can't be read
+    pub fn load(path: &Path) -> Self {
+        let tokens = match fs::read_to_string(path) {
+            Ok(data) => serde_json::from_str(&data).unwrap_or_else(|err| {
+                warn!("<session> ignoring invalid sessions file: {}", err);
+                HashMap::new()
+            }),
+            Err(_) => HashMap::new(),
+        };
+
+        Self {
+            path: path.to_path_buf(),
+            tokens,
+        }
+    }
+
+    fn key(server: &str, username: &str) -> String {
+        format!("{}@{}", username, server)
+    }
+
+    pub fn token(&self, server: &str, username: &str) -> Option<String> {
+        self.tokens.get(&Self::key(server, username)).cloned()
+    }
+
+    /// stores a token, `None` forgets it
+    pub fn set_token(&mut self, server: &str, username: &str, token: Option<String>) {
+        let key = Self::key(server, username);
+
+        match token {
+            Some(token) => self.tokens.insert(key, token),
+            None => self.tokens.remove(&key),
+        };
+
+        if let Err(err) = self.save() {
+            error!("<session> could not save the sessions: {}", err);
+        }
+    }
+
+    fn save(&self) -> io::Result<()> {
+        fs::write(&self.path, serde_json::to_string_pretty(&self.tokens)?)
+    }
+}
+
+/// Identity the player logs into a server with.
+pub struct Login {
+    pub username: String,
+    server: String,
+    sessions: SessionStore,
+}
+
+impl Login {
+    pub fn new(username: String, info: &RemoteInfo) -> Self {
+        Self {
+            username,
+            server: format!("{}:{}", info.ip, info.port),
+            sessions: SessionStore::load(Path::new(SESSIONS_PATH)),
+        }
+    }
+
+    /// token from the last session on this server, if any
+    pub fn token(&self) -> Option<String> {
+        self.sessions.token(&self.server, &self.username)
+    }
+
+    pub fn accepted(&mut self, token: String) {
+        self.sessions
+            .set_token(&self.server, &self.username, Some(token));
+    }
+
+    /// forgets the token the server did not accept, the next login starts a new session
+    pub fn forget_token(&mut self) {
+        self.sessions.set_token(&self.server, &self.username, None);
+    }
+}
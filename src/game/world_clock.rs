@@ -0,0 +1,179 @@
+use crate::network::{DAY_LENGTH, TICKS_PER_SECOND};
+
+use std::f64::consts::PI;
+use std::time::Duration;
+
+/// time of day local worlds start at, 0 is midnight
+const MORNING: u64 = DAY_LENGTH / 4;
+/// an error above this many ticks is fixed at once instead of being smoothed out
+const SNAP_THRESHOLD: f64 = TICKS_PER_SECOND;
+/// how long a small error takes to be corrected
+const CORRECTION_TIME: f64 = 2.0;
+/// bounds on how much faster or slower the clock runs while correcting its drift
+const MIN_RATE: f64 = 0.9;
+const MAX_RATE: f64 = 1.1;
+
+/// World tick and time of day, following the server clock in networked games. Small errors are
+/// corrected by running the clock slightly faster or slower so it never jumps backwards.
+pub struct WorldClock {
+    tick: f64,
+    /// added to the tick to get the time of day
+    day_offset: u64,
+    rate: f64,
+    synchronized: bool,
+}
+
+impl WorldClock {
+    pub fn new() -> Self {
+        Self {
+            tick: 0.0,
+            day_offset: MORNING,
+            rate: 1.0,
+            synchronized: false,
+        }
+    }
+
+    pub fn update(&mut self, time_delta: f64) {
+        self.tick += time_delta * TICKS_PER_SECOND * self.rate;
+    }
+
+    /// catches up with the time sent by the server, `latency` is how long it took to get here
+    pub fn synchronize(&mut self, tick: u64, day_time: u32, latency: Duration) {
+        let server_tick = tick as f64 + latency.as_secs_f64() * TICKS_PER_SECOND;
+        let error = server_tick - self.tick;
+
+        if !self.synchronized || error.abs() > SNAP_THRESHOLD {
+            debug!("<clock> synchronized on tick {} ({:+.1})", tick, error);
+            self.tick = server_tick;
+            self.rate = 1.0;
+            self.synchronized = true;
+        } else {
+            let rate = 1.0 + error / (CORRECTION_TIME * TICKS_PER_SECOND);
+            self.rate = rate.clamp(MIN_RATE, MAX_RATE);
+        }
+
+        let day_offset = day_time as i64 - tick as i64;
+        self.day_offset = day_offset.rem_euclid(DAY_LENGTH as i64) as u64;
+    }
+
+    /// forgets the server clock, to call when the connection is lost
+    pub fn reset(&mut self) {
+        self.synchronized = false;
+        self.rate = 1.0;
+    }
+
+    // TODO: remove this dead_code attribute when game logic runs on ticks
+    #[allow(dead_code)]
+    pub fn tick(&self) -> u64 {
+        self.tick as u64
+    }
+
+    /// world time in seconds, for animations
+    // TODO: remove this dead_code attribute when we have animations
+    #[allow(dead_code)]
+    pub fn time(&self) -> f64 {
+        self.tick / TICKS_PER_SECOND
+    }
+
+    /// fraction of the day between 0 and 1, 0 is midnight and 0.5 is noon
+    pub fn time_of_day(&self) -> f32 {
+        let day_tick = (self.tick + self.day_offset as f64) % DAY_LENGTH as f64;
+        (day_tick / DAY_LENGTH as f64) as f32
+    }
+
+    /// how bright the sun is, 0 at night and 1 during the day with a transition around 6 and 18
+    pub fn daylight(&self) -> f32 {
+        let height = (2.0 * PI * (self.time_of_day() as f64 - 0.25)).sin();
+        (height * 2.0 + 0.5).clamp(0.0, 1.0) as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn synchronized(tick: u64) -> WorldClock {
+        let mut clock = WorldClock::new();
+        clock.synchronize(tick, 0, Duration::from_secs(0));
+        clock
+    }
+
+    #[test]
+    fn the_first_time_is_taken_as_is_plus_the_latency() {
+        let mut clock = WorldClock::new();
+        clock.synchronize(1000, 0, Duration::from_millis(100));
+
+        assert_eq!(clock.tick(), 1002);
+        assert_eq!(clock.rate, 1.0);
+    }
+
+    #[test]
+    fn small_errors_change_the_rate() {
+        let mut clock = synchronized(1000);
+        clock.synchronize(1002, 0, Duration::from_secs(0));
+        assert_eq!(clock.tick(), 1000);
+        assert!((clock.rate - 1.05).abs() < 1e-9);
+
+        clock.update(1.0);
+        assert_eq!(clock.tick(), 1021);
+
+        let mut clock = synchronized(1000);
+        clock.synchronize(998, 0, Duration::from_secs(0));
+        assert_eq!(clock.tick(), 1000);
+        assert!((clock.rate - 0.95).abs() < 1e-9);
+    }
+
+    #[test]
+    fn the_rate_is_clamped() {
+        let mut clock = synchronized(1000);
+        clock.synchronize(1000 + SNAP_THRESHOLD as u64, 0, Duration::from_secs(0));
+        assert_eq!(clock.rate, MAX_RATE);
+
+        clock.synchronize(1000 - SNAP_THRESHOLD as u64, 0, Duration::from_secs(0));
+        assert_eq!(clock.rate, MIN_RATE);
+        assert_eq!(clock.tick(), 1000);
+    }
+
+    #[test]
+    fn large_errors_snap() {
+        let mut clock = synchronized(1000);
+        clock.synchronize(900, 0, Duration::from_secs(0));
+        assert_eq!(clock.tick(), 900);
+
+        clock.synchronize(5000, 0, Duration::from_secs(0));
+        assert_eq!(clock.tick(), 5000);
+        assert_eq!(clock.rate, 1.0);
+    }
+
+    #[test]
+    fn a_reset_clock_snaps_on_the_next_time() {
+        let mut clock = synchronized(1000);
+        clock.synchronize(1010, 0, Duration::from_secs(0));
+        clock.reset();
+
+        assert_eq!(clock.rate, 1.0);
+        clock.synchronize(1010, 0, Duration::from_secs(0));
+        assert_eq!(clock.tick(), 1010);
+    }
+
+    #[test]
+    fn the_day_follows_the_server_day_time() {
+        let mut clock = WorldClock::new();
+        assert_eq!(clock.time_of_day(), 0.25);
+        assert_eq!(clock.daylight(), 0.5);
+
+        // the day time is independent of the tick
+        clock.synchronize(100, (DAY_LENGTH / 2) as u32, Duration::from_secs(0));
+        assert_eq!(clock.time_of_day(), 0.5);
+        assert_eq!(clock.daylight(), 1.0);
+
+        clock.update(DAY_LENGTH as f64 / 2.0 / TICKS_PER_SECOND);
+        assert_eq!(clock.time_of_day(), 0.0);
+        assert_eq!(clock.daylight(), 0.0);
+
+        // a day time behind the tick wraps around
+        clock.synchronize(20000, 100, Duration::from_secs(0));
+        assert_eq!(clock.tick(), 20000);
+        assert!((clock.time_of_day() - 100.0 / DAY_LENGTH as f32).abs() < 1e-6);
+    }
+}
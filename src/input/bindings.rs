@@ -0,0 +1,285 @@
+use glutin::event::VirtualKeyCode;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// where key bindings are kept, relative to the working directory
+pub const BINDINGS_PATH: &str = "bindings.json";
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Action {
+    MoveForward,
+    MoveBackward,
+    MoveLeft,
+    MoveRight,
+    MoveUp,
+    MoveDown,
+    OpenChat,
+    ShowPlayerList,
+    DecreaseRenderDistance,
+    IncreaseRenderDistance,
+    ReplayPause,
+    ReplaySpeedUp,
+    ReplaySlowDown,
+    ReplaySeekBackward,
+    ReplaySeekForward,
+}
+
+impl Action {
+    pub const ALL: [Action; 15] = [
+        Action::MoveForward,
+        Action::MoveBackward,
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::MoveUp,
+        Action::MoveDown,
+        Action::OpenChat,
+        Action::ShowPlayerList,
+        Action::DecreaseRenderDistance,
+        Action::IncreaseRenderDistance,
+        Action::ReplayPause,
+        Action::ReplaySpeedUp,
+        Action::ReplaySlowDown,
+        Action::ReplaySeekBackward,
+        Action::ReplaySeekForward,
+    ];
+
+    fn default_keys(&self) -> Vec<VirtualKeyCode> {
+        let key = match self {
+            Action::MoveForward => VirtualKeyCode::W,
+            Action::MoveBackward => VirtualKeyCode::S,
+            Action::MoveLeft => VirtualKeyCode::A,
+            Action::MoveRight => VirtualKeyCode::D,
+            Action::MoveUp => VirtualKeyCode::Space,
+            Action::MoveDown => VirtualKeyCode::LShift,
+            Action::OpenChat => VirtualKeyCode::T,
+            Action::ShowPlayerList => VirtualKeyCode::Tab,
+            Action::DecreaseRenderDistance => VirtualKeyCode::J,
+            Action::IncreaseRenderDistance => VirtualKeyCode::K,
+            Action::ReplayPause => VirtualKeyCode::P,
+            Action::ReplaySpeedUp => VirtualKeyCode::Up,
+            Action::ReplaySlowDown => VirtualKeyCode::Down,
+            Action::ReplaySeekBackward => VirtualKeyCode::Left,
+            Action::ReplaySeekForward => VirtualKeyCode::Right,
+        };
+
+        vec![key]
+    }
+}
+
+/// Value between -1 and 1 driven by a pair of opposite actions.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Axis {
+    /// left to right
+    MoveX,
+    /// down to up
+    MoveY,
+    /// backward to forward
+    MoveZ,
+}
+
+impl Axis {
+    /// actions pushing the axis toward -1 and 1
+    pub fn actions(&self) -> (Action, Action) {
+        match self {
+            Axis::MoveX => (Action::MoveLeft, Action::MoveRight),
+            Axis::MoveY => (Action::MoveDown, Action::MoveUp),
+            Axis::MoveZ => (Action::MoveBackward, Action::MoveForward),
+        }
+    }
+}
+
+/// Keys bound to each action, saved to a JSON file so they can be changed without rebuilding.
+/// Any number of keys can trigger the same action.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Bindings {
+    /// `None` when the file has mistakes, it is then left for the player to fix, and for
+    /// bindings read from an input recording
+    #[serde(skip)]
+    path: Option<PathBuf>,
+    keys: BTreeMap<Action, Vec<VirtualKeyCode>>,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        Self {
+            path: Some(PathBuf::from(BINDINGS_PATH)),
+            keys: Action::ALL
+                .iter()
+                .map(|action| (*action, action.default_keys()))
+                .collect(),
+        }
+    }
+}
+
+impl Bindings {
+    /// actions missing from the file or bound to invalid keys get their default keys. The file
+    /// is created if it does not exist and completed if actions are missing, but never written
+    /// when it is invalid.
+    pub fn load(path: &Path) -> Self {
+        let (mut keys, valid) = match fs::read_to_string(path) {
+            Ok(data) => match serde_json::from_str(&data) {
+                Ok(entries) => Self::parse(entries),
+                Err(err) => {
+                    warn!("<bindings> ignoring invalid bindings file: {}", err);
+                    (BTreeMap::new(), false)
+                }
+            },
+            Err(_) => (BTreeMap::new(), true),
+        };
+
+        let complete = Action::ALL.iter().all(|action| keys.contains_key(action));
+
+        for action in Action::ALL.iter() {
+            keys.entry(*action).or_insert_with(|| action.default_keys());
+        }
+
+        let bindings = Self {
+            path: if valid {
+                Some(path.to_path_buf())
+            } else {
+                None
+            },
+            keys,
+        };
+
+        if !complete {
+            bindings.persist();
+        }
+
+        bindings
+    }
+
+    /// keys of every valid entry, warning about the others, and whether they were all valid
+    fn parse(entries: BTreeMap<String, Value>) -> (BTreeMap<Action, Vec<VirtualKeyCode>>, bool) {
+        let mut keys = BTreeMap::new();
+        let mut valid = true;
+
+        for (name, value) in entries {
+            let action = match serde_json::from_value(Value::String(name.clone())) {
+                Ok(action) => action,
+                Err(_) => {
+                    warn!("<bindings> ignoring unknown action {}", name);
+                    valid = false;
+                    continue;
+                }
+            };
+
+            let key_names: Vec<String> = match serde_json::from_value(value) {
+                Ok(key_names) => key_names,
+                Err(_) => {
+                    warn!("<bindings> {} should be a list of key names", name);
+                    valid = false;
+                    continue;
+                }
+            };
+
+            let mut bound = Vec::new();
+            for key_name in key_names.iter() {
+                match serde_json::from_value(Value::String(key_name.clone())) {
+                    Ok(key) => bound.push(key),
+                    Err(_) => warn!("<bindings> unknown key {} for {}", key_name, name),
+                }
+            }
+
+            // a typo falls back to the default keys rather than leaving the action unbound
+            if bound.len() == key_names.len() {
+                keys.insert(action, bound);
+            } else {
+                valid = false;
+            }
+        }
+
+        (keys, valid)
+    }
+
+    pub fn keys(&self, action: Action) -> &[VirtualKeyCode] {
+        self.keys.get(&action).map_or(&[], |keys| keys.as_slice())
+    }
+
+    /// adds a key triggering the action
+    // TODO: remove this dead_code attribute when we have a menu for this
+    #[allow(dead_code)]
+    pub fn bind(&mut self, action: Action, key: VirtualKeyCode) {
+        let keys = self.keys.entry(action).or_default();
+
+        if !keys.contains(&key) {
+            keys.push(key);
+            self.persist();
+        }
+    }
+
+    // TODO: remove this dead_code attribute when we have a menu for this
+    #[allow(dead_code)]
+    pub fn unbind(&mut self, action: Action, key: VirtualKeyCode) {
+        if let Some(keys) = self.keys.get_mut(&action) {
+            keys.retain(|bound| *bound != key);
+            self.persist();
+        }
+    }
+
+    fn persist(&self) {
+        let path = match self.path.as_ref() {
+            Some(path) => path,
+            None => return,
+        };
+
+        if let Err(err) = self.save(path) {
+            error!("<bindings> could not save the bindings: {}", err);
+        }
+    }
+
+    fn save(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, serde_json::to_string_pretty(&self.keys)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::env;
+
+    /// loads the bindings from a file holding `data` and returns what the file holds after
+    fn load(name: &str, data: &str) -> (Bindings, String) {
+        let path = env::temp_dir().join(format!("bindings-{}.json", name));
+        fs::write(&path, data).unwrap();
+
+        let bindings = Bindings::load(&path);
+        let saved = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        (bindings, saved)
+    }
+
+    #[test]
+    fn missing_actions_are_saved_with_their_default_keys() {
+        let (bindings, saved) = load("missing", r#"{ "MoveForward": ["Up"] }"#);
+
+        assert_eq!(bindings.keys(Action::MoveForward), &[VirtualKeyCode::Up]);
+        assert_eq!(bindings.keys(Action::MoveUp), &[VirtualKeyCode::Space]);
+        assert!(saved.contains("MoveUp"));
+    }
+
+    #[test]
+    fn invalid_entries_use_the_default_keys_and_keep_the_file() {
+        let data = r#"{ "MoveForward": ["Up", "NotAKey"], "MoveUp": ["Return"], "Fly": ["F"] }"#;
+        let (bindings, saved) = load("invalid", data);
+
+        assert_eq!(bindings.keys(Action::MoveForward), &[VirtualKeyCode::W]);
+        assert_eq!(bindings.keys(Action::MoveUp), &[VirtualKeyCode::Return]);
+        assert_eq!(saved, data);
+    }
+
+    #[test]
+    fn an_unreadable_file_is_kept() {
+        let data = r#"{ "MoveForward": ["#;
+        let (bindings, saved) = load("unreadable", data);
+
+        assert_eq!(bindings.keys(Action::MoveForward), &[VirtualKeyCode::W]);
+        assert_eq!(saved, data);
+    }
+}
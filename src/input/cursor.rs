@@ -1,6 +1,7 @@
+use serde::{Deserialize, Serialize};
 use std::ops;
 
-#[derive(Debug, Copy, Clone, Default)]
+#[derive(Debug, Copy, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct CursorDelta {
     pub x: f64,
     pub y: f64,
@@ -32,6 +33,10 @@ impl CursorHandler {
         &self.delta
     }
 
+    pub fn set_delta(&mut self, delta: CursorDelta) {
+        self.delta = delta;
+    }
+
     pub fn clear(&mut self) {
         self.delta = CursorDelta::default();
     }
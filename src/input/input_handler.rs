@@ -1,36 +1,252 @@
-use glutin::event::{KeyboardInput, VirtualKeyCode};
+use glutin::event::{
+    ElementState, KeyboardInput, ModifiersState, MouseButton, MouseScrollDelta, VirtualKeyCode,
+};
 
-use crate::input::{CursorDelta, CursorHandler, KeyboardHandler};
+use crate::input::{
+    Action, Axis, Bindings, CursorDelta, CursorHandler, InputFrame, KeyTimer, KeyboardHandler,
+    LineBuffer, MouseHandler, ScrollDelta, TextHistory, TextInput, TextInputEvent, TimingSettings,
+};
+use std::time::Duration;
 
 #[derive(Debug, Default)]
 pub struct InputHandler {
+    bindings: Bindings,
     keyboard: KeyboardHandler,
+    timer: KeyTimer,
     cursor: CursorHandler,
+    mouse: MouseHandler,
+    modifiers: ModifiersState,
+    text_input: Option<TextInput>,
+    text_history: TextHistory,
+    /// text cut or copied while typing
+    paste_buffer: String,
 }
 
 impl InputHandler {
+    pub fn new(bindings: Bindings) -> Self {
+        Self {
+            bindings,
+            ..Default::default()
+        }
+    }
+
+    pub fn bindings(&self) -> &Bindings {
+        &self.bindings
+    }
+
+    pub fn bindings_mut(&mut self) -> &mut Bindings {
+        &mut self.bindings
+    }
+
+    // TODO: remove this dead_code attribute when we have a menu for this
+    #[allow(dead_code)]
+    pub fn set_timing_settings(&mut self, settings: TimingSettings) {
+        self.timer.set_settings(settings);
+    }
+
+    /// to call once per frame after the events were processed and before the game reads them
+    pub fn update(&mut self, time_delta: f64) {
+        let (pressed, just_pressed, _) = self.keyboard.state();
+        self.timer.update(time_delta, &pressed, &just_pressed);
+    }
+
+    /// while typing, key presses edit the line and are hidden from the rest of the game
     pub fn process_keyboard(&mut self, input: KeyboardInput) {
-        self.keyboard.process(input)
+        match (self.text_input.as_mut(), input.virtual_keycode) {
+            (Some(text_input), Some(keycode)) => {
+                if input.state == ElementState::Pressed {
+                    text_input.process_key(keycode, self.modifiers, &mut self.paste_buffer);
+                } else {
+                    self.keyboard.process(input);
+                }
+            }
+            _ => self.keyboard.process(input),
+        }
+    }
+
+    pub fn process_modifiers(&mut self, modifiers: ModifiersState) {
+        self.modifiers = modifiers;
     }
 
     pub fn process_cursor(&mut self, input: (f64, f64)) {
         self.cursor.process(input)
     }
 
-    pub fn is_key_pressed(&self, keycode: VirtualKeyCode) -> bool {
-        self.keyboard.is_pressed(keycode)
+    pub fn process_mouse_button(&mut self, button: MouseButton, state: ElementState) {
+        self.mouse.process_button(button, state)
+    }
+
+    pub fn process_scroll(&mut self, delta: MouseScrollDelta) {
+        self.mouse.process_scroll(delta)
+    }
+
+    pub fn process_character(&mut self, c: char) {
+        if let Some(text_input) = self.text_input.as_mut() {
+            text_input.process_char(c);
+        }
+    }
+
+    /// captures typed characters until the line is submitted or cancelled, keys are not
+    /// reported as pressed in the meantime
+    pub fn start_text_input(&mut self, max_length: usize) {
+        self.text_input = Some(TextInput::new(max_length, &self.text_history));
+    }
+
+    pub fn is_typing(&self) -> bool {
+        self.text_input.is_some()
+    }
+
+    /// line being typed, `None` when not typing
+    pub fn typed_line(&self) -> Option<&LineBuffer> {
+        self.text_input.as_ref().map(|text_input| text_input.line())
+    }
+
+    /// stops the text input and returns how it ended, once it did. Submitted lines are added
+    /// to the history.
+    pub fn take_text_event(&mut self) -> Option<TextInputEvent> {
+        let event = self.text_input.as_mut()?.take_event()?;
+        self.text_input = None;
+
+        if let TextInputEvent::Submitted(text) = &event {
+            self.text_history.push(text.clone());
+        }
+
+        Some(event)
+    }
+
+    fn is_key_pressed(&self, keycode: VirtualKeyCode) -> bool {
+        !self.is_typing() && self.keyboard.is_pressed(keycode)
+    }
+
+    fn just_pressed(&self, keycode: VirtualKeyCode) -> bool {
+        !self.is_typing() && self.keyboard.just_pressed(keycode)
+    }
+
+    fn just_released(&self, keycode: VirtualKeyCode) -> bool {
+        !self.is_typing() && self.keyboard.just_released(keycode)
+    }
+
+    /// indicates one of the keys bound to the action is held
+    pub fn is_action_pressed(&self, action: Action) -> bool {
+        self.bindings
+            .keys(action)
+            .iter()
+            .any(|key| self.is_key_pressed(*key))
+    }
+
+    /// indicates one of the keys bound to the action was just pressed
+    pub fn action_just_pressed(&self, action: Action) -> bool {
+        self.bindings
+            .keys(action)
+            .iter()
+            .any(|key| self.just_pressed(*key))
     }
 
-    pub fn just_pressed(&self, keycode: VirtualKeyCode) -> bool {
-        self.keyboard.just_pressed(keycode)
+    /// indicates one of the keys bound to the action was just released
+    // TODO: remove this dead_code attribute when a control acts on release
+    #[allow(dead_code)]
+    pub fn action_just_released(&self, action: Action) -> bool {
+        self.bindings
+            .keys(action)
+            .iter()
+            .any(|key| self.just_released(*key))
+    }
+
+    /// how long the action has been held, counting from the key held the longest
+    // TODO: remove this dead_code attribute when blocks can be broken by holding
+    #[allow(dead_code)]
+    pub fn action_hold_duration(&self, action: Action) -> Option<Duration> {
+        if self.is_typing() {
+            return None;
+        }
+
+        self.bindings
+            .keys(action)
+            .iter()
+            .filter_map(|key| self.timer.hold_duration(*key))
+            .max()
+    }
+
+    /// indicates a key bound to the action was just pressed twice in a row quickly enough
+    // TODO: remove this dead_code attribute when we have sprinting or flying
+    #[allow(dead_code)]
+    pub fn action_double_tapped(&self, action: Action) -> bool {
+        !self.is_typing()
+            && self
+                .bindings
+                .keys(action)
+                .iter()
+                .any(|key| self.timer.double_tapped(*key))
+    }
+
+    /// indicates a key bound to the action has just been held long enough, reported once
+    // TODO: remove this dead_code attribute when blocks can be broken by holding
+    #[allow(dead_code)]
+    pub fn action_long_pressed(&self, action: Action) -> bool {
+        !self.is_typing()
+            && self
+                .bindings
+                .keys(action)
+                .iter()
+                .any(|key| self.timer.long_pressed(*key))
+    }
+
+    /// -1, 0 or 1 depending on which of the actions of the axis are held
+    pub fn axis(&self, axis: Axis) -> f32 {
+        let (negative, positive) = axis.actions();
+        self.is_action_pressed(positive) as i32 as f32
+            - self.is_action_pressed(negative) as i32 as f32
     }
 
     pub fn get_cursor_delta(&self) -> &CursorDelta {
         self.cursor.get_delta()
     }
 
+    // TODO: remove this dead_code attribute when blocks can be broken by holding
+    #[allow(dead_code)]
+    pub fn is_mouse_pressed(&self, button: MouseButton) -> bool {
+        self.mouse.is_pressed(button)
+    }
+
+    pub fn mouse_just_pressed(&self, button: MouseButton) -> bool {
+        self.mouse.just_pressed(button)
+    }
+
+    // TODO: remove this dead_code attribute when we have clickable UI
+    #[allow(dead_code)]
+    pub fn mouse_just_released(&self, button: MouseButton) -> bool {
+        self.mouse.just_released(button)
+    }
+
+    /// scrolled distance in lines since the last frame
+    // TODO: remove this dead_code attribute when we have a hotbar
+    #[allow(dead_code)]
+    pub fn get_scroll_delta(&self) -> &ScrollDelta {
+        self.mouse.get_scroll()
+    }
+
+    /// keyboard and cursor state of the current frame, for input recordings
+    pub fn frame(&self) -> InputFrame {
+        let (pressed, just_pressed, just_released) = self.keyboard.state();
+
+        InputFrame {
+            pressed,
+            just_pressed,
+            just_released,
+            cursor_delta: *self.cursor.get_delta(),
+        }
+    }
+
+    /// replaces the keyboard and cursor state with a recorded frame
+    pub fn set_frame(&mut self, frame: &InputFrame) {
+        self.keyboard
+            .set_state(&frame.pressed, &frame.just_pressed, &frame.just_released);
+        self.cursor.set_delta(frame.cursor_delta);
+    }
+
     pub fn clear(&mut self) {
         self.cursor.clear();
+        self.mouse.clear();
         self.keyboard.clear();
     }
 }
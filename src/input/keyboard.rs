@@ -1,6 +1,12 @@
 use glutin::event::{ElementState, KeyboardInput, VirtualKeyCode};
 use std::collections::HashSet;
 
+fn sorted(keys: &HashSet<VirtualKeyCode>) -> Vec<VirtualKeyCode> {
+    let mut keys: Vec<VirtualKeyCode> = keys.iter().cloned().collect();
+    keys.sort_by_key(|key| *key as u32);
+    keys
+}
+
 #[derive(Debug, Default)]
 pub struct KeyboardHandler {
     pressed: HashSet<VirtualKeyCode>,
@@ -45,6 +51,38 @@ impl KeyboardHandler {
         self.pressed_since_clear.contains(&keycode)
     }
 
+    /// indicates a previously pressed key was just released
+    pub fn just_released(&self, keycode: VirtualKeyCode) -> bool {
+        self.released_since_clear.contains(&keycode)
+    }
+
+    /// keys held, pressed and released since the last clear, each sorted
+    pub fn state(
+        &self,
+    ) -> (
+        Vec<VirtualKeyCode>,
+        Vec<VirtualKeyCode>,
+        Vec<VirtualKeyCode>,
+    ) {
+        (
+            sorted(&self.pressed),
+            sorted(&self.pressed_since_clear),
+            sorted(&self.released_since_clear),
+        )
+    }
+
+    /// replaces the whole state, as given by `state`
+    pub fn set_state(
+        &mut self,
+        pressed: &[VirtualKeyCode],
+        just_pressed: &[VirtualKeyCode],
+        just_released: &[VirtualKeyCode],
+    ) {
+        self.pressed = pressed.iter().cloned().collect();
+        self.pressed_since_clear = just_pressed.iter().cloned().collect();
+        self.released_since_clear = just_released.iter().cloned().collect();
+    }
+
     /// to call at the end of each gameloop
     pub fn clear(&mut self) {
         self.pressed_since_clear.clear();
@@ -0,0 +1,301 @@
+/// Single line of editable text with a cursor and an optional selection. Positions are counted
+/// in characters, between 0 and the length of the line.
+#[derive(Debug, Clone, Default)]
+pub struct LineBuffer {
+    chars: Vec<char>,
+    cursor: usize,
+    /// other end of the selection, the cursor being the end that moves
+    anchor: Option<usize>,
+    max_length: usize,
+}
+
+impl LineBuffer {
+    pub fn new(max_length: usize) -> Self {
+        Self {
+            max_length,
+            ..Default::default()
+        }
+    }
+
+    pub fn text(&self) -> String {
+        self.chars.iter().collect()
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// start and end of the selected characters, `None` if nothing is selected
+    pub fn selection(&self) -> Option<(usize, usize)> {
+        let anchor = self.anchor?;
+
+        if anchor == self.cursor {
+            None
+        } else {
+            Some((anchor.min(self.cursor), anchor.max(self.cursor)))
+        }
+    }
+
+    pub fn selected_text(&self) -> Option<String> {
+        let (start, end) = self.selection()?;
+        Some(self.chars[start..end].iter().collect())
+    }
+
+    /// replaces the whole line and moves the cursor at its end
+    pub fn set_text(&mut self, text: &str) {
+        self.chars = text.chars().take(self.max_length).collect();
+        self.cursor = self.chars.len();
+        self.anchor = None;
+    }
+
+    /// types text over the selection, control characters become spaces and whatever does not
+    /// fit is dropped
+    pub fn insert(&mut self, text: &str) {
+        self.delete_selection();
+
+        let room = self.max_length.saturating_sub(self.chars.len());
+        let inserted: Vec<char> = text
+            .chars()
+            .map(|c| if c.is_control() { ' ' } else { c })
+            .take(room)
+            .collect();
+
+        let count = inserted.len();
+        self.chars.splice(self.cursor..self.cursor, inserted);
+        self.cursor += count;
+    }
+
+    pub fn backspace(&mut self) {
+        if !self.delete_selection() && self.cursor > 0 {
+            self.cursor -= 1;
+            self.chars.remove(self.cursor);
+        }
+    }
+
+    pub fn delete(&mut self) {
+        if !self.delete_selection() && self.cursor < self.chars.len() {
+            self.chars.remove(self.cursor);
+        }
+    }
+
+    /// deletes from the start of the previous word to the cursor
+    pub fn delete_word_before(&mut self) {
+        if !self.delete_selection() {
+            let start = self.previous_word(self.cursor);
+            self.chars.drain(start..self.cursor);
+            self.cursor = start;
+        }
+    }
+
+    /// deletes from the cursor to the end of the next word
+    pub fn delete_word_after(&mut self) {
+        if !self.delete_selection() {
+            let end = self.next_word(self.cursor);
+            self.chars.drain(self.cursor..end);
+        }
+    }
+
+    /// removes the selected characters, returns whether there were any
+    pub fn delete_selection(&mut self) -> bool {
+        match self.selection() {
+            Some((start, end)) => {
+                self.chars.drain(start..end);
+                self.cursor = start;
+                self.anchor = None;
+                true
+            }
+            None => {
+                self.anchor = None;
+                false
+            }
+        }
+    }
+
+    pub fn select_all(&mut self) {
+        self.anchor = Some(0);
+        self.cursor = self.chars.len();
+    }
+
+    /// moves the cursor to `position`, extending the selection if `select` is set
+    pub fn move_to(&mut self, position: usize, select: bool) {
+        if select {
+            self.anchor.get_or_insert(self.cursor);
+        } else {
+            self.anchor = None;
+        }
+
+        self.cursor = position.min(self.chars.len());
+    }
+
+    pub fn move_left(&mut self, select: bool) {
+        // moving without selecting collapses the selection on its start
+        let position = match self.selection() {
+            Some((start, _)) if !select => start,
+            _ => self.cursor.saturating_sub(1),
+        };
+
+        self.move_to(position, select);
+    }
+
+    pub fn move_right(&mut self, select: bool) {
+        let position = match self.selection() {
+            Some((_, end)) if !select => end,
+            _ => self.cursor + 1,
+        };
+
+        self.move_to(position, select);
+    }
+
+    pub fn move_word_left(&mut self, select: bool) {
+        self.move_to(self.previous_word(self.cursor), select);
+    }
+
+    pub fn move_word_right(&mut self, select: bool) {
+        self.move_to(self.next_word(self.cursor), select);
+    }
+
+    pub fn move_home(&mut self, select: bool) {
+        self.move_to(0, select);
+    }
+
+    pub fn move_end(&mut self, select: bool) {
+        self.move_to(self.chars.len(), select);
+    }
+
+    /// start of the word before `position`, skipping the spaces in between
+    fn previous_word(&self, mut position: usize) -> usize {
+        while position > 0 && self.chars[position - 1].is_whitespace() {
+            position -= 1;
+        }
+
+        while position > 0 && !self.chars[position - 1].is_whitespace() {
+            position -= 1;
+        }
+
+        position
+    }
+
+    /// end of the word after `position`, skipping the spaces in between
+    fn next_word(&self, mut position: usize) -> usize {
+        while position < self.chars.len() && self.chars[position].is_whitespace() {
+            position += 1;
+        }
+
+        while position < self.chars.len() && !self.chars[position].is_whitespace() {
+            position += 1;
+        }
+
+        position
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(text: &str) -> LineBuffer {
+        let mut line = LineBuffer::new(20);
+        line.set_text(text);
+        line
+    }
+
+    #[test]
+    fn typing_goes_at_the_cursor() {
+        let mut line = line("helo");
+        line.move_left(false);
+        line.insert("l");
+
+        assert_eq!(line.text(), "hello");
+        assert_eq!(line.cursor(), 4);
+
+        line.move_home(false);
+        line.move_left(false);
+        line.insert("\tsay");
+        assert_eq!(line.text(), " sayhello");
+    }
+
+    #[test]
+    fn typing_replaces_the_selection() {
+        let mut line = line("hello world");
+        line.move_word_left(true);
+
+        assert_eq!(line.selection(), Some((6, 11)));
+        assert_eq!(line.selected_text().unwrap(), "world");
+
+        line.insert("there");
+        assert_eq!(line.text(), "hello there");
+        assert_eq!(line.selection(), None);
+    }
+
+    #[test]
+    fn moving_without_shift_collapses_the_selection() {
+        let mut line = line("hello world");
+        line.move_home(false);
+        line.move_right(true);
+        line.move_right(true);
+        line.move_right(false);
+        assert_eq!((line.cursor(), line.selection()), (2, None));
+
+        line.select_all();
+        line.move_left(false);
+        assert_eq!((line.cursor(), line.selection()), (0, None));
+
+        line.move_left(false);
+        assert_eq!(line.cursor(), 0);
+        line.move_end(false);
+        line.move_right(false);
+        assert_eq!(line.cursor(), 11);
+    }
+
+    #[test]
+    fn word_jumps_skip_the_spaces_in_between() {
+        let mut line = line("one  two three");
+        line.move_home(false);
+
+        line.move_word_right(false);
+        assert_eq!(line.cursor(), 3);
+        line.move_word_right(false);
+        assert_eq!(line.cursor(), 8);
+
+        line.move_word_left(false);
+        assert_eq!(line.cursor(), 5);
+        line.move_word_left(false);
+        assert_eq!(line.cursor(), 0);
+    }
+
+    #[test]
+    fn whole_words_are_deleted() {
+        let mut line = line("one  two three");
+        line.delete_word_before();
+        assert_eq!(line.text(), "one  two ");
+
+        line.move_home(false);
+        line.move_word_right(false);
+        line.delete_word_after();
+        assert_eq!(line.text(), "one ");
+
+        line.backspace();
+        line.delete();
+        assert_eq!(line.text(), "on");
+
+        line.select_all();
+        line.delete_word_before();
+        assert_eq!(line.text(), "");
+    }
+
+    #[test]
+    fn text_beyond_the_max_length_is_dropped() {
+        let mut line = LineBuffer::new(5);
+        line.set_text("too long");
+        assert_eq!(line.text(), "too l");
+
+        line.move_home(false);
+        line.insert("!");
+        assert_eq!(line.text(), "too l");
+
+        line.move_right(true);
+        line.insert("éèê");
+        assert_eq!(line.text(), "éoo l");
+        assert_eq!(line.cursor(), 1);
+    }
+}
@@ -1,8 +1,20 @@
+mod bindings;
 mod cursor;
 mod input_handler;
 mod keyboard;
+mod line_buffer;
+mod mouse;
+mod recording;
+mod text;
+mod timing;
 
+pub use self::bindings::{Action, Axis, Bindings, BINDINGS_PATH};
 pub use self::cursor::CursorDelta;
 pub use self::cursor::CursorHandler;
 pub use self::input_handler::InputHandler;
 pub use self::keyboard::KeyboardHandler;
+pub use self::line_buffer::LineBuffer;
+pub use self::mouse::{MouseHandler, ScrollDelta};
+pub use self::recording::{InputFrame, InputPlayback, InputRecorder, PLAYBACK_TIME_DELTA};
+pub use self::text::{TextHistory, TextInput, TextInputEvent};
+pub use self::timing::{KeyTimer, TimingSettings};
@@ -0,0 +1,158 @@
+use glutin::event::{ElementState, MouseButton, MouseScrollDelta};
+use std::collections::HashSet;
+use std::ops;
+
+/// touchpads scroll in pixels, this many make up a line like a wheel notch
+const PIXELS_PER_LINE: f64 = 20.0;
+
+/// Scrolled distance in lines, positive y is away from the user.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct ScrollDelta {
+    pub x: f32,
+    pub y: f32,
+}
+
+impl ops::AddAssign for ScrollDelta {
+    fn add_assign(&mut self, other: Self) {
+        *self = Self {
+            x: self.x + other.x,
+            y: self.y + other.y,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct MouseHandler {
+    pressed: HashSet<MouseButton>,
+    pressed_since_clear: HashSet<MouseButton>,
+    released_since_clear: HashSet<MouseButton>,
+    scroll: ScrollDelta,
+}
+
+impl MouseHandler {
+    pub fn process_button(&mut self, button: MouseButton, state: ElementState) {
+        match state {
+            ElementState::Pressed => {
+                if !self.is_pressed(button) {
+                    self.pressed_since_clear.insert(button);
+                }
+                self.pressed.insert(button);
+            }
+            ElementState::Released => {
+                if self.is_pressed(button) {
+                    self.released_since_clear.insert(button);
+                }
+                self.pressed.remove(&button);
+            }
+        };
+    }
+
+    pub fn process_scroll(&mut self, delta: MouseScrollDelta) {
+        self.scroll += match delta {
+            MouseScrollDelta::LineDelta(x, y) => ScrollDelta { x, y },
+            MouseScrollDelta::PixelDelta(position) => ScrollDelta {
+                x: (position.x / PIXELS_PER_LINE) as f32,
+                y: (position.y / PIXELS_PER_LINE) as f32,
+            },
+        };
+    }
+
+    /// indicates a button is currently being held
+    pub fn is_pressed(&self, button: MouseButton) -> bool {
+        self.pressed.contains(&button)
+    }
+
+    /// indicates a previously unpressed button was just pressed
+    pub fn just_pressed(&self, button: MouseButton) -> bool {
+        self.pressed_since_clear.contains(&button)
+    }
+
+    /// indicates a previously pressed button was just released
+    pub fn just_released(&self, button: MouseButton) -> bool {
+        self.released_since_clear.contains(&button)
+    }
+
+    /// scrolled distance since the last clear
+    pub fn get_scroll(&self) -> &ScrollDelta {
+        &self.scroll
+    }
+
+    /// to call at the end of each gameloop
+    pub fn clear(&mut self) {
+        self.pressed_since_clear.clear();
+        self.released_since_clear.clear();
+        self.scroll = ScrollDelta::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use glutin::dpi::PhysicalPosition;
+
+    const LEFT: MouseButton = MouseButton::Left;
+
+    #[test]
+    fn presses_and_releases_are_reported_until_cleared() {
+        let mut mouse = MouseHandler::default();
+
+        mouse.process_button(LEFT, ElementState::Pressed);
+        assert!(mouse.is_pressed(LEFT));
+        assert!(mouse.just_pressed(LEFT));
+        assert!(!mouse.just_pressed(MouseButton::Right));
+
+        mouse.clear();
+        assert!(mouse.is_pressed(LEFT));
+        assert!(!mouse.just_pressed(LEFT));
+
+        mouse.process_button(LEFT, ElementState::Released);
+        assert!(!mouse.is_pressed(LEFT));
+        assert!(mouse.just_released(LEFT));
+
+        mouse.clear();
+        assert!(!mouse.just_released(LEFT));
+    }
+
+    #[test]
+    fn repeated_events_are_not_new_edges() {
+        let mut mouse = MouseHandler::default();
+
+        mouse.process_button(LEFT, ElementState::Released);
+        assert!(!mouse.just_released(LEFT));
+
+        mouse.process_button(LEFT, ElementState::Pressed);
+        mouse.clear();
+        mouse.process_button(LEFT, ElementState::Pressed);
+        assert!(!mouse.just_pressed(LEFT));
+    }
+
+    #[test]
+    fn a_click_within_a_frame_is_both_pressed_and_released() {
+        let mut mouse = MouseHandler::default();
+
+        mouse.process_button(LEFT, ElementState::Pressed);
+        mouse.process_button(LEFT, ElementState::Released);
+
+        assert!(mouse.just_pressed(LEFT));
+        assert!(mouse.just_released(LEFT));
+        assert!(!mouse.is_pressed(LEFT));
+    }
+
+    #[test]
+    fn pixels_are_scrolled_as_lines() {
+        let mut mouse = MouseHandler::default();
+
+        mouse.process_scroll(MouseScrollDelta::LineDelta(0.0, 1.0));
+        mouse.process_scroll(MouseScrollDelta::PixelDelta(PhysicalPosition::new(
+            -10.0, 30.0,
+        )));
+
+        let scroll = mouse.get_scroll();
+        assert_eq!(scroll.x, -0.5);
+        assert_eq!(scroll.y, 2.5);
+
+        mouse.clear();
+        assert_eq!(mouse.get_scroll().y, 0.0);
+    }
+}
@@ -0,0 +1,104 @@
+use crate::input::{Bindings, CursorDelta};
+
+use glutin::event::VirtualKeyCode;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, ErrorKind, Write};
+use std::path::Path;
+
+/// time step of every frame played back, whatever the frame rate was when recording
+pub const PLAYBACK_TIME_DELTA: f64 = 1.0 / 60.0;
+/// bumped when `InputFrame` or `Bindings` change
+const RECORDING_VERSION: u32 = 2;
+
+/// State of the input handler during a single frame. Keys are sorted so identical states
+/// compare and serialize the same.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct InputFrame {
+    pub pressed: Vec<VirtualKeyCode>,
+    pub just_pressed: Vec<VirtualKeyCode>,
+    pub just_released: Vec<VirtualKeyCode>,
+    pub cursor_delta: CursorDelta,
+}
+
+fn invalid_data(err: bincode::Error) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, err)
+}
+
+pub struct InputRecorder {
+    writer: BufWriter<File>,
+}
+
+impl InputRecorder {
+    /// frames hold raw keys, so the bindings they are meant for are saved along with them
+    pub fn create(path: &Path, bindings: &Bindings) -> io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        bincode::serialize_into(&mut writer, &RECORDING_VERSION).map_err(invalid_data)?;
+        bincode::serialize_into(&mut writer, bindings).map_err(invalid_data)?;
+
+        Ok(Self { writer })
+    }
+
+    pub fn record(&mut self, frame: &InputFrame) {
+        if let Err(err) = bincode::serialize_into(&mut self.writer, frame) {
+            error!("<input> could not write frame: {}", err);
+        }
+    }
+}
+
+impl Drop for InputRecorder {
+    fn drop(&mut self) {
+        let _ = self.writer.flush();
+    }
+}
+
+/// Frames of an input recording, handed out one per game update.
+pub struct InputPlayback {
+    bindings: Bindings,
+    frames: VecDeque<InputFrame>,
+}
+
+impl InputPlayback {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let mut reader = BufReader::new(File::open(path)?);
+
+        let version: u32 = bincode::deserialize_from(&mut reader).map_err(invalid_data)?;
+        if version != RECORDING_VERSION {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "input recording version {} is not supported, expected version {}",
+                    version, RECORDING_VERSION
+                ),
+            ));
+        }
+
+        let bindings = bincode::deserialize_from(&mut reader).map_err(invalid_data)?;
+
+        let mut frames = VecDeque::new();
+        loop {
+            match bincode::deserialize_from(&mut reader) {
+                Ok(frame) => frames.push_back(frame),
+                Err(err) => match *err {
+                    bincode::ErrorKind::Io(ref err) if err.kind() == ErrorKind::UnexpectedEof => {
+                        break
+                    }
+                    _ => return Err(invalid_data(err)),
+                },
+            }
+        }
+
+        Ok(Self { bindings, frames })
+    }
+
+    /// bindings in use when recording, to play the frames back with
+    pub fn bindings(&self) -> &Bindings {
+        &self.bindings
+    }
+
+    /// next frame to feed the game, `None` once the recording is over
+    pub fn next_frame(&mut self) -> Option<InputFrame> {
+        self.frames.pop_front()
+    }
+}
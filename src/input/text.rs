@@ -0,0 +1,259 @@
+use crate::input::LineBuffer;
+
+use glutin::event::{ModifiersState, VirtualKeyCode};
+use std::collections::VecDeque;
+
+const MAX_HISTORY: usize = 50;
+
+#[derive(Debug)]
+pub enum TextInputEvent {
+    Submitted(String),
+    Cancelled,
+}
+
+/// Lines submitted so far, oldest first, recalled with the up and down arrows.
+#[derive(Debug, Default)]
+pub struct TextHistory {
+    lines: VecDeque<String>,
+}
+
+impl TextHistory {
+    /// empty lines and repeats of the last line are not kept
+    pub fn push(&mut self, line: String) {
+        if line.trim().is_empty() || self.lines.back() == Some(&line) {
+            return;
+        }
+
+        if self.lines.len() >= MAX_HISTORY {
+            self.lines.pop_front();
+        }
+
+        self.lines.push_back(line);
+    }
+}
+
+/// Line of text typed by the player. Printable characters come from `ReceivedCharacter` events
+/// while editing keys come from keyboard events, so they repeat when held.
+#[derive(Debug)]
+pub struct TextInput {
+    line: LineBuffer,
+    /// history as it was when the input started
+    history: Vec<String>,
+    /// index of the history line being shown, `None` while editing the new line
+    recalled: Option<usize>,
+    /// new line kept aside while going through the history
+    draft: String,
+    event: Option<TextInputEvent>,
+}
+
+impl TextInput {
+    pub fn new(max_length: usize, history: &TextHistory) -> Self {
+        Self {
+            line: LineBuffer::new(max_length),
+            history: history.lines.iter().cloned().collect(),
+            recalled: None,
+            draft: String::new(),
+            event: None,
+        }
+    }
+
+    pub fn process_char(&mut self, c: char) {
+        // control characters are sent along with the editing keys, which are handled on their own
+        if !c.is_control() {
+            self.line.insert(&c.to_string());
+        }
+    }
+
+    /// `paste_buffer` holds the text cut or copied from previous lines, the system clipboard is
+    /// not used
+    pub fn process_key(
+        &mut self,
+        key: VirtualKeyCode,
+        modifiers: ModifiersState,
+        paste_buffer: &mut String,
+    ) {
+        let select = modifiers.shift();
+        let word = modifiers.ctrl() || modifiers.alt();
+        let command = modifiers.ctrl() || modifiers.logo();
+
+        match key {
+            VirtualKeyCode::Return | VirtualKeyCode::NumpadEnter => {
+                self.event = Some(TextInputEvent::Submitted(self.line.text()))
+            }
+            VirtualKeyCode::Escape => self.event = Some(TextInputEvent::Cancelled),
+            VirtualKeyCode::Back if word => self.line.delete_word_before(),
+            VirtualKeyCode::Back => self.line.backspace(),
+            VirtualKeyCode::Delete if word => self.line.delete_word_after(),
+            VirtualKeyCode::Delete => self.line.delete(),
+            VirtualKeyCode::Left if word => self.line.move_word_left(select),
+            VirtualKeyCode::Left => self.line.move_left(select),
+            VirtualKeyCode::Right if word => self.line.move_word_right(select),
+            VirtualKeyCode::Right => self.line.move_right(select),
+            VirtualKeyCode::Home => self.line.move_home(select),
+            VirtualKeyCode::End => self.line.move_end(select),
+            VirtualKeyCode::Up => self.recall_previous(),
+            VirtualKeyCode::Down => self.recall_next(),
+            VirtualKeyCode::A if command => self.line.select_all(),
+            VirtualKeyCode::C if command => {
+                if let Some(text) = self.line.selected_text() {
+                    *paste_buffer = text;
+                }
+            }
+            VirtualKeyCode::X if command => {
+                if let Some(text) = self.line.selected_text() {
+                    *paste_buffer = text;
+                    self.line.delete_selection();
+                }
+            }
+            VirtualKeyCode::V if command => self.line.insert(paste_buffer),
+            _ => (),
+        }
+    }
+
+    pub fn line(&self) -> &LineBuffer {
+        &self.line
+    }
+
+    /// the event ending the input, once
+    pub fn take_event(&mut self) -> Option<TextInputEvent> {
+        self.event.take()
+    }
+
+    fn recall_previous(&mut self) {
+        let index = match self.recalled {
+            None if !self.history.is_empty() => self.history.len() - 1,
+            Some(index) if index > 0 => index - 1,
+            _ => return,
+        };
+
+        self.recall(Some(index));
+    }
+
+    fn recall_next(&mut self) {
+        let index = match self.recalled {
+            Some(index) if index + 1 < self.history.len() => Some(index + 1),
+            Some(_) => None,
+            None => return,
+        };
+
+        self.recall(index);
+    }
+
+    /// shows a line from the history, `None` goes back to the new line
+    fn recall(&mut self, index: Option<usize>) {
+        if self.recalled.is_none() {
+            self.draft = self.line.text();
+        }
+
+        self.recalled = index;
+
+        match index {
+            Some(index) => self.line.set_text(&self.history[index]),
+            None => self.line.set_text(&self.draft),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history(lines: &[&str]) -> TextHistory {
+        let mut history = TextHistory::default();
+        for line in lines {
+            history.push(line.to_string());
+        }
+
+        history
+    }
+
+    fn press(input: &mut TextInput, key: VirtualKeyCode, modifiers: ModifiersState) {
+        input.process_key(key, modifiers, &mut String::new());
+    }
+
+    fn type_text(input: &mut TextInput, text: &str) {
+        for c in text.chars() {
+            input.process_char(c);
+        }
+    }
+
+    #[test]
+    fn empty_and_repeated_lines_are_not_kept() {
+        let history = history(&["first", "  ", "second", "second", "first"]);
+        assert_eq!(history.lines, vec!["first", "second", "first"]);
+    }
+
+    #[test]
+    fn only_the_last_lines_are_kept() {
+        let lines: Vec<String> = (0..MAX_HISTORY + 5).map(|n| n.to_string()).collect();
+        let history = history(&lines.iter().map(|line| line.as_str()).collect::<Vec<_>>());
+
+        assert_eq!(history.lines.len(), MAX_HISTORY);
+        assert_eq!(history.lines.front().unwrap(), "5");
+    }
+
+    #[test]
+    fn history_is_recalled_and_the_draft_restored() {
+        let history = history(&["first", "second"]);
+        let mut input = TextInput::new(20, &history);
+        let none = ModifiersState::empty();
+        type_text(&mut input, "draft");
+
+        press(&mut input, VirtualKeyCode::Up, none);
+        assert_eq!(input.line().text(), "second");
+        press(&mut input, VirtualKeyCode::Up, none);
+        press(&mut input, VirtualKeyCode::Up, none);
+        assert_eq!(input.line().text(), "first");
+
+        press(&mut input, VirtualKeyCode::Down, none);
+        assert_eq!(input.line().text(), "second");
+        press(&mut input, VirtualKeyCode::Down, none);
+        assert_eq!(input.line().text(), "draft");
+        press(&mut input, VirtualKeyCode::Down, none);
+        assert_eq!(input.line().text(), "draft");
+    }
+
+    #[test]
+    fn recalled_lines_can_be_edited_and_submitted() {
+        let history = history(&["hello"]);
+        let mut input = TextInput::new(20, &history);
+
+        press(&mut input, VirtualKeyCode::Up, ModifiersState::empty());
+        type_text(&mut input, " world");
+        press(&mut input, VirtualKeyCode::Return, ModifiersState::empty());
+
+        match input.take_event() {
+            Some(TextInputEvent::Submitted(text)) => assert_eq!(text, "hello world"),
+            event => panic!("unexpected {:?}", event),
+        }
+        assert!(input.take_event().is_none());
+    }
+
+    #[test]
+    fn cut_text_is_pasted_up_to_the_max_length() {
+        let mut input = TextInput::new(8, &TextHistory::default());
+        let mut paste_buffer = String::new();
+        type_text(&mut input, "abc def");
+
+        input.process_key(
+            VirtualKeyCode::Left,
+            ModifiersState::CTRL | ModifiersState::SHIFT,
+            &mut paste_buffer,
+        );
+        input.process_key(VirtualKeyCode::X, ModifiersState::CTRL, &mut paste_buffer);
+        assert_eq!(paste_buffer, "def");
+        assert_eq!(input.line().text(), "abc ");
+
+        input.process_key(VirtualKeyCode::V, ModifiersState::CTRL, &mut paste_buffer);
+        input.process_key(VirtualKeyCode::V, ModifiersState::CTRL, &mut paste_buffer);
+        assert_eq!(input.line().text(), "abc defd");
+    }
+
+    #[test]
+    fn control_characters_are_not_typed() {
+        let mut input = TextInput::new(20, &TextHistory::default());
+        type_text(&mut input, "a\u{8}\rb\u{7f}");
+
+        assert_eq!(input.line().text(), "ab");
+    }
+}
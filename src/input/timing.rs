@@ -0,0 +1,202 @@
+use glutin::event::VirtualKeyCode;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+#[derive(Debug, Copy, Clone)]
+pub struct TimingSettings {
+    /// longest time between two presses of a key for them to count as a double tap
+    pub double_tap_window: Duration,
+    /// how long a key must be held to count as a long press
+    pub long_press: Duration,
+}
+
+impl Default for TimingSettings {
+    fn default() -> Self {
+        Self {
+            double_tap_window: Duration::from_millis(250),
+            long_press: Duration::from_millis(500),
+        }
+    }
+}
+
+/// Times how long keys are held and detects double taps and long presses. Time only moves
+/// forward with the frames given to `update`, so recorded inputs played back with a fixed time
+/// step are timed the same way.
+#[derive(Debug, Default)]
+pub struct KeyTimer {
+    settings: TimingSettings,
+    /// seconds since the timer started
+    time: f64,
+    /// when each held key went down
+    pressed_at: HashMap<VirtualKeyCode, f64>,
+    /// when each key was last pressed, forgotten once it made a double tap
+    last_press: HashMap<VirtualKeyCode, f64>,
+    double_tapped: HashSet<VirtualKeyCode>,
+    long_pressed: HashSet<VirtualKeyCode>,
+}
+
+impl KeyTimer {
+    pub fn set_settings(&mut self, settings: TimingSettings) {
+        self.settings = settings;
+    }
+
+    /// to call once per frame before the keys are queried
+    pub fn update(
+        &mut self,
+        time_delta: f64,
+        pressed: &[VirtualKeyCode],
+        just_pressed: &[VirtualKeyCode],
+    ) {
+        let previous_time = self.time;
+        self.time += time_delta;
+        self.double_tapped.clear();
+        self.long_pressed.clear();
+
+        let double_tap_window = self.settings.double_tap_window.as_secs_f64();
+        for key in just_pressed.iter() {
+            match self.last_press.get(key) {
+                Some(last) if self.time - last <= double_tap_window => {
+                    // a third tap starts a new double tap instead of completing another one
+                    self.double_tapped.insert(*key);
+                    self.last_press.remove(key);
+                }
+                _ => {
+                    self.last_press.insert(*key, self.time);
+                }
+            }
+
+            self.pressed_at.insert(*key, self.time);
+        }
+
+        self.pressed_at.retain(|key, _| pressed.contains(key));
+
+        // long presses are reported once, on the frame the key has been held long enough
+        let long_press = self.settings.long_press.as_secs_f64();
+        for (key, pressed_at) in self.pressed_at.iter() {
+            if self.time - pressed_at >= long_press && previous_time - pressed_at < long_press {
+                self.long_pressed.insert(*key);
+            }
+        }
+    }
+
+    /// how long the key has been held, `None` if it is not
+    pub fn hold_duration(&self, key: VirtualKeyCode) -> Option<Duration> {
+        self.pressed_at
+            .get(&key)
+            .map(|pressed_at| Duration::from_secs_f64(self.time - pressed_at))
+    }
+
+    /// indicates the key was just pressed a second time within the double tap window
+    pub fn double_tapped(&self, key: VirtualKeyCode) -> bool {
+        self.double_tapped.contains(&key)
+    }
+
+    /// indicates the key has just been held for the long press duration
+    pub fn long_pressed(&self, key: VirtualKeyCode) -> bool {
+        self.long_pressed.contains(&key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: VirtualKeyCode = VirtualKeyCode::W;
+    /// a power of two, so frames add up to the settings exactly
+    const FRAME: f64 = 1.0 / 16.0;
+
+    /// runs a frame with the key pressed on it, held or released
+    fn tap(timer: &mut KeyTimer) {
+        timer.update(FRAME, &[KEY], &[KEY]);
+    }
+
+    fn hold(timer: &mut KeyTimer) {
+        timer.update(FRAME, &[KEY], &[]);
+    }
+
+    fn release(timer: &mut KeyTimer, frames: usize) {
+        for _ in 0..frames {
+            timer.update(FRAME, &[], &[]);
+        }
+    }
+
+    #[test]
+    fn two_taps_within_the_window_are_a_double_tap() {
+        let mut timer = KeyTimer::default();
+        tap(&mut timer);
+        assert!(!timer.double_tapped(KEY));
+
+        // the second press lands exactly at the end of the window
+        release(&mut timer, 3);
+        tap(&mut timer);
+        assert!(timer.double_tapped(KEY));
+
+        hold(&mut timer);
+        assert!(!timer.double_tapped(KEY));
+    }
+
+    #[test]
+    fn taps_further_apart_are_not_a_double_tap() {
+        let mut timer = KeyTimer::default();
+        tap(&mut timer);
+        release(&mut timer, 4);
+        tap(&mut timer);
+        assert!(!timer.double_tapped(KEY));
+
+        // but the late tap can start one
+        release(&mut timer, 1);
+        tap(&mut timer);
+        assert!(timer.double_tapped(KEY));
+    }
+
+    #[test]
+    fn a_third_tap_starts_over() {
+        let mut timer = KeyTimer::default();
+        let mut double_taps = Vec::new();
+
+        for _ in 0..4 {
+            tap(&mut timer);
+            double_taps.push(timer.double_tapped(KEY));
+            release(&mut timer, 1);
+        }
+
+        assert_eq!(double_taps, vec![false, true, false, true]);
+    }
+
+    #[test]
+    fn long_presses_are_reported_once() {
+        let mut timer = KeyTimer::default();
+        let mut frames = Vec::new();
+
+        tap(&mut timer);
+        for frame in 1..=32 {
+            hold(&mut timer);
+            if timer.long_pressed(KEY) {
+                frames.push(frame);
+            }
+        }
+
+        assert_eq!(frames, vec![8]);
+        assert_eq!(timer.hold_duration(KEY), Some(Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn keys_released_early_are_not_long_pressed() {
+        let mut timer = KeyTimer::default();
+        timer.set_settings(TimingSettings {
+            long_press: Duration::from_secs(1),
+            ..TimingSettings::default()
+        });
+
+        for _ in 0..3 {
+            tap(&mut timer);
+            for _ in 0..15 {
+                hold(&mut timer);
+                assert!(!timer.long_pressed(KEY));
+            }
+
+            release(&mut timer, 1);
+            assert_eq!(timer.hold_duration(KEY), None);
+        }
+    }
+}
@@ -17,7 +17,10 @@ extern crate serde;
 extern crate serde_json;
 
 use crate::game::{Game, GameType};
-use crate::input::InputHandler;
+use crate::input::{
+    Bindings, InputHandler, InputPlayback, InputRecorder, BINDINGS_PATH, PLAYBACK_TIME_DELTA,
+};
+use crate::network::{MockServer, NetworkConditions, RemoteInfo};
 use crate::render::display::Display;
 
 use core::utils::{
@@ -27,14 +30,105 @@ use core::utils::{
 };
 use glutin::event::{DeviceEvent, Event, WindowEvent};
 use glutin::event_loop::{ControlFlow, EventLoop};
-use std::io;
+use std::env;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::thread;
 use std::time::{Duration, Instant};
 
 const FPS_REFRESH_TIMEOUT: u64 = 1;
+const DISCOVERY_TIMEOUT: Duration = Duration::from_secs(1);
 const FRAME_RATE_CAP: u32 = 60;
 const PKG_NAME: &'static str = env!("CARGO_PKG_NAME");
 const PKG_VERSION: &'static str = env!("CARGO_PKG_VERSION");
 
+/// value following `name` on the command line
+fn argument(name: &str) -> Option<String> {
+    let mut args = env::args().skip_while(|arg| arg != name);
+    args.next()?;
+    args.next()
+}
+
+/// indicates `name` was passed on the command line
+fn flag(name: &str) -> bool {
+    env::args().any(|arg| arg == name)
+}
+
+/// value following `name` on the command line, parsed
+fn parsed_argument<T: FromStr>(name: &str) -> io::Result<Option<T>> {
+    argument(name)
+        .map(|value| {
+            value.parse().map_err(|_| {
+                io::Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("invalid value for {}: {}", name, value),
+                )
+            })
+        })
+        .transpose()
+}
+
+/// network conditions to simulate, read from a JSON file and overridden by single flags
+fn network_conditions() -> io::Result<Option<NetworkConditions>> {
+    let mut conditions = match argument("--network-conditions") {
+        Some(path) => Some(NetworkConditions::load(Path::new(&path))?),
+        None => None,
+    };
+
+    if let Some(latency) = parsed_argument("--latency")? {
+        conditions.get_or_insert_with(Default::default).latency = latency;
+    }
+
+    if let Some(jitter) = parsed_argument("--jitter")? {
+        conditions.get_or_insert_with(Default::default).jitter = jitter;
+    }
+
+    if let Some(loss) = parsed_argument("--loss")? {
+        conditions.get_or_insert_with(Default::default).loss = loss;
+    }
+
+    if let Some(duplication) = parsed_argument("--duplication")? {
+        conditions.get_or_insert_with(Default::default).duplication = duplication;
+    }
+
+    if let Some(reordering) = parsed_argument("--reordering")? {
+        conditions.get_or_insert_with(Default::default).reordering = reordering;
+    }
+
+    if let Some(conditions) = conditions.as_ref() {
+        conditions.validate()?;
+    }
+
+    Ok(conditions)
+}
+
+/// looks for servers on the local network and picks the closest compatible one
+fn discover_server() -> io::Result<RemoteInfo> {
+    let servers = network::discover(DISCOVERY_TIMEOUT)?;
+
+    for server in servers.iter() {
+        if let Some(details) = server.details.as_ref() {
+            info!("found {}:{} {}", server.ip, server.port, details);
+        }
+    }
+
+    servers
+        .into_iter()
+        .find(|server| {
+            server
+                .details
+                .as_ref()
+                .map_or(false, |details| details.is_joinable())
+        })
+        .ok_or_else(|| {
+            io::Error::new(
+                ErrorKind::NotFound,
+                "no server to join found on the local network",
+            )
+        })
+}
+
 fn main() -> io::Result<()> {
     if cfg!(debug_assertions) {
         logging::init(vec![
@@ -60,18 +154,85 @@ fn main() -> io::Result<()> {
     let display = Display::new(PKG_NAME, &event_loop);
     let (width, height) = display.size();
 
-    let mut input_handler = InputHandler::default();
+    let mut input_handler = InputHandler::new(Bindings::load(Path::new(BINDINGS_PATH)));
+
+    let username = argument("--username")
+        .or_else(|| env::var("USER").ok())
+        .unwrap_or_else(|| String::from("player"));
 
     #[cfg(not(feature = "remote"))]
-    let game_type = GameType::Local;
+    let default_game_type = GameType::Local;
     #[cfg(feature = "remote")]
-    let game_type = GameType::Remote {
-        info: crate::network::RemoteInfo::new(String::from("localhost"), 25565),
+    let default_game_type = GameType::Remote {
+        info: crate::network::RemoteInfo::new(String::from("localhost"), 25565).with_transport(
+            if flag("--tcp") {
+                crate::network::TransportType::Tcp
+            } else {
+                crate::network::TransportType::Udp
+            },
+        ),
+        username: username.clone(),
     };
 
+    let mut game_type = if let Some(path) = argument("--replay") {
+        GameType::Replay {
+            path: PathBuf::from(path),
+        }
+    } else if flag("--discover") {
+        GameType::Remote {
+            info: discover_server()?,
+            username,
+        }
+    } else if flag("--mock-server") {
+        // on the network, a LAN server can be found from other clients but only serves this one
+        let (server, info) = if flag("--lan") {
+            let (mut server, info) = MockServer::bind(0)?;
+            server.announce(String::from(PKG_NAME), info.port)?;
+            (server, info)
+        } else {
+            MockServer::memory(0)
+        };
+
+        thread::spawn(move || server.run());
+
+        GameType::Remote { info, username }
+    } else {
+        default_game_type
+    };
+
+    if let GameType::Remote { info, .. } = &mut game_type {
+        info.conditions = network_conditions()?;
+    }
+
     let mut game = Game::new(game_type)?;
     game.resize(width, height);
 
+    if let Some(path) = argument("--record") {
+        game.start_recording(Path::new(&path))?;
+    }
+
+    let mut input_recorder = match argument("--record-input") {
+        Some(path) => Some(InputRecorder::create(
+            Path::new(&path),
+            input_handler.bindings(),
+        )?),
+        None => None,
+    };
+
+    // recorded frames replace the keyboard and mouse, at a fixed frame rate
+    let mut input_playback = match argument("--play-input") {
+        Some(path) => Some(InputPlayback::open(Path::new(&path))?),
+        None => None,
+    };
+
+    // the recorded keys only make sense with the bindings they were recorded with
+    if let Some(playback) = input_playback.as_ref() {
+        *input_handler.bindings_mut() = playback.bindings().clone();
+    }
+
+    // the window stays open until the server got the disconnect
+    let mut closing = false;
+
     let mut fps: u32 = 0;
     let mut last_time = Instant::now();
     let mut last_fps_update = Instant::now();
@@ -85,6 +246,14 @@ fn main() -> io::Result<()> {
         },
         Event::WindowEvent { event, .. } => match event {
             WindowEvent::KeyboardInput { input, .. } => input_handler.process_keyboard(input),
+            WindowEvent::ModifiersChanged(modifiers) => {
+                input_handler.process_modifiers(modifiers)
+            }
+            WindowEvent::MouseInput { button, state, .. } => {
+                input_handler.process_mouse_button(button, state)
+            }
+            WindowEvent::MouseWheel { delta, .. } => input_handler.process_scroll(delta),
+            WindowEvent::ReceivedCharacter(c) => input_handler.process_character(c),
             WindowEvent::Resized(size) => {
                 display.resize(size);
                 game.resize(size.width as usize, size.height as usize);
@@ -96,11 +265,14 @@ fn main() -> io::Result<()> {
                     new_inner_size.height as usize,
                 );
             }
-            WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
+            WindowEvent::CloseRequested => {
+                game.disconnect();
+                closing = true;
+            }
             _ => (),
         },
         Event::MainEventsCleared => {
-            let time_delta = last_time.elapsed().as_secs_f64();
+            let mut time_delta = last_time.elapsed().as_secs_f64();
             last_time = Instant::now();
 
             if last_fps_update.elapsed().as_secs() >= FPS_REFRESH_TIMEOUT {
@@ -112,10 +284,48 @@ fn main() -> io::Result<()> {
                     info!("FPS: {}", fps);
                 }
 
+                if let Some(stats) = game.network_stats() {
+                    debug!("network: {}", stats);
+                }
+
                 last_fps_update = Instant::now();
             }
 
-            game.update(time_delta, &input_handler);
+            if let Some(playback) = input_playback.as_mut() {
+                match playback.next_frame() {
+                    Some(frame) => {
+                        input_handler.set_frame(&frame);
+                        time_delta = PLAYBACK_TIME_DELTA;
+                    }
+                    None => {
+                        info!("input playback finished");
+                        *control_flow = ControlFlow::Exit;
+                        return;
+                    }
+                }
+            }
+
+            if let Some(recorder) = input_recorder.as_mut() {
+                recorder.record(&input_handler.frame());
+            }
+
+            input_handler.update(time_delta);
+
+            game.update(time_delta, &mut input_handler);
+
+            match game.connection_state() {
+                Some(state) if state.is_active() => (),
+                _ if closing => *control_flow = ControlFlow::Exit,
+                Some(state) => {
+                    match game.login_error() {
+                        Some(reason) => error!("could not log in: {}", reason),
+                        None => error!("could not continue the game: {}", state),
+                    }
+
+                    *control_flow = ControlFlow::Exit;
+                }
+                None => (),
+            }
 
             input_handler.clear();
 
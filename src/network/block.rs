@@ -0,0 +1,149 @@
+use core::block::Block;
+use core::chunk::{ChunkGridCoordinate, CHUNK_DEPTH, CHUNK_HEIGHT, CHUNK_WIDTH};
+use core::world::{World, WorldCoordinate};
+use serde::{Deserialize, Serialize};
+
+pub const AIR: Block = Block { id: 0 };
+
+/// position of a block in the world
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct BlockCoordinate {
+    pub x: i64,
+    pub y: i64,
+    pub z: i64,
+}
+
+impl BlockCoordinate {
+    /// block holding the position
+    pub fn from_position(position: WorldCoordinate) -> Self {
+        Self {
+            x: position.x.floor() as i64,
+            y: position.y.floor() as i64,
+            z: position.z.floor() as i64,
+        }
+    }
+
+    pub fn chunk(&self) -> ChunkGridCoordinate {
+        ChunkGridCoordinate::new(
+            self.x.div_euclid(CHUNK_WIDTH as i64),
+            self.z.div_euclid(CHUNK_DEPTH as i64),
+        )
+    }
+
+    /// position inside its chunk, `None` above or below the world
+    fn local(&self) -> Option<(usize, usize, usize)> {
+        if self.y < 0 || self.y >= CHUNK_HEIGHT as i64 {
+            return None;
+        }
+
+        Some((
+            self.x.rem_euclid(CHUNK_WIDTH as i64) as usize,
+            self.y as usize,
+            self.z.rem_euclid(CHUNK_DEPTH as i64) as usize,
+        ))
+    }
+
+    /// chunks whose mesh shows this block, a block on a border is also seen from the neighbor
+    pub fn meshes(&self) -> Vec<ChunkGridCoordinate> {
+        let chunk = self.chunk();
+        let mut meshes = vec![chunk];
+
+        let x = self.x.rem_euclid(CHUNK_WIDTH as i64);
+        let z = self.z.rem_euclid(CHUNK_DEPTH as i64);
+
+        if x == 0 {
+            meshes.push(ChunkGridCoordinate::new(chunk.x - 1, chunk.z));
+        } else if x == CHUNK_WIDTH as i64 - 1 {
+            meshes.push(ChunkGridCoordinate::new(chunk.x + 1, chunk.z));
+        }
+
+        if z == 0 {
+            meshes.push(ChunkGridCoordinate::new(chunk.x, chunk.z - 1));
+        } else if z == CHUNK_DEPTH as i64 - 1 {
+            meshes.push(ChunkGridCoordinate::new(chunk.x, chunk.z + 1));
+        }
+
+        meshes
+    }
+
+    /// `None` if the chunk is not loaded or the block is out of the world
+    pub fn block(&self, world: &World) -> Option<Block> {
+        let (x, y, z) = self.local()?;
+        Some(world.chunks.get(&self.chunk())?.block(x, y, z))
+    }
+
+    /// replaces the block and returns the previous one, `None` if nothing changed
+    pub fn set_block(&self, world: &mut World, block: Block) -> Option<Block> {
+        let (x, y, z) = self.local()?;
+        let chunk = world.chunks.get_mut(&self.chunk())?;
+        let previous = chunk.block(x, y, z);
+
+        if previous.id == block.id {
+            return None;
+        }
+
+        chunk.set_block(x, y, z, block);
+        Some(previous)
+    }
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub enum BlockAction {
+    Break,
+    Place(Block),
+}
+
+impl BlockAction {
+    /// block left at the coordinate once the action is done
+    pub fn block(&self) -> Block {
+        match self {
+            BlockAction::Break => AIR,
+            BlockAction::Place(block) => *block,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(x: i64, z: i64) -> BlockCoordinate {
+        BlockCoordinate { x, y: 64, z }
+    }
+
+    #[test]
+    fn negative_coordinates_belong_to_negative_chunks() {
+        assert_eq!(at(0, 0).chunk(), ChunkGridCoordinate::new(0, 0));
+        assert_eq!(at(-1, -1).chunk(), ChunkGridCoordinate::new(-1, -1));
+        assert_eq!(at(-16, 16).chunk(), ChunkGridCoordinate::new(-1, 1));
+        assert_eq!(at(-17, 0).chunk(), ChunkGridCoordinate::new(-2, 0));
+    }
+
+    #[test]
+    fn blocks_on_borders_are_meshed_by_both_chunks() {
+        let chunk = |x, z| ChunkGridCoordinate::new(x, z);
+
+        assert_eq!(at(5, 5).meshes(), vec![chunk(0, 0)]);
+        assert_eq!(at(0, 5).meshes(), vec![chunk(0, 0), chunk(-1, 0)]);
+        assert_eq!(at(15, 5).meshes(), vec![chunk(0, 0), chunk(1, 0)]);
+        assert_eq!(at(-1, 5).meshes(), vec![chunk(-1, 0), chunk(0, 0)]);
+        assert_eq!(
+            at(16, 31).meshes(),
+            vec![chunk(1, 1), chunk(0, 1), chunk(1, 2)]
+        );
+    }
+
+    #[test]
+    fn blocks_out_of_the_world_have_no_position_in_their_chunk() {
+        let below = BlockCoordinate { x: 0, y: -1, z: 0 };
+        let above = BlockCoordinate {
+            x: 0,
+            y: CHUNK_HEIGHT as i64,
+            z: 0,
+        };
+
+        assert!(below.local().is_none());
+        assert!(above.local().is_none());
+        assert_eq!(at(-1, 17).local(), Some((15, 64, 1)));
+    }
+}
@@ -0,0 +1,390 @@
+use crate::network::packet::{Delivery, Message, Packet, Sequence};
+
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+/// resend timeout until the round trip time is known, and its lower bound
+const MIN_RESEND_TIMEOUT: Duration = Duration::from_millis(100);
+const MAX_RESEND_TIMEOUT: Duration = Duration::from_secs(1);
+/// the timeout doubles with every resend of a message, up to this many times
+const MAX_RESEND_DOUBLINGS: u32 = 2;
+const ACK_BITS: u32 = 32;
+/// how far ahead of the next expected reliable sequence we accept to buffer messages
+const RECEIVE_WINDOW: Sequence = 1024;
+
+struct PendingMessage {
+    payload: Vec<u8>,
+    last_sent: Instant,
+    resends: u32,
+}
+
+/// Reliability layer on top of an unreliable datagram socket. The channel never touches the
+/// socket itself, it only turns payloads into packets and packets back into payloads.
+pub struct Channel {
+    next_reliable: Sequence,
+    next_sequenced: Sequence,
+    pending: BTreeMap<Sequence, PendingMessage>,
+    resend_timeout: Duration,
+
+    expected_reliable: Sequence,
+    received: BTreeMap<Sequence, Vec<u8>>,
+    last_sequenced: Option<Sequence>,
+    ack_required: bool,
+}
+
+impl Channel {
+    pub fn new() -> Self {
+        Self {
+            next_reliable: 0,
+            next_sequenced: 0,
+            pending: BTreeMap::new(),
+            resend_timeout: MIN_RESEND_TIMEOUT,
+
+            expected_reliable: 0,
+            received: BTreeMap::new(),
+            last_sequenced: None,
+            ack_required: false,
+        }
+    }
+
+    /// wraps a payload in a packet ready to be sent, reliable payloads are kept until acked
+    pub fn send(&mut self, delivery: Delivery, payload: Vec<u8>) -> Packet {
+        let sequence = match delivery {
+            Delivery::Reliable => {
+                let sequence = self.next_reliable;
+                self.next_reliable += 1;
+
+                self.pending.insert(
+                    sequence,
+                    PendingMessage {
+                        payload: payload.clone(),
+                        last_sent: Instant::now(),
+                        resends: 0,
+                    },
+                );
+
+                sequence
+            }
+            Delivery::Sequenced => {
+                let sequence = self.next_sequenced;
+                self.next_sequenced += 1;
+                sequence
+            }
+        };
+
+        self.packet(Some(Message {
+            delivery,
+            sequence,
+            payload,
+        }))
+    }
+
+    /// handles an incoming packet and returns the payloads that are ready to be consumed, in order
+    pub fn receive(&mut self, packet: Packet) -> Vec<Vec<u8>> {
+        self.acknowledge(packet.ack, packet.ack_bits);
+
+        let mut payloads = Vec::new();
+
+        let message = match packet.message {
+            Some(message) => message,
+            None => return payloads,
+        };
+
+        match message.delivery {
+            Delivery::Reliable => {
+                self.ack_required = true;
+
+                let sequence = message.sequence;
+                if sequence < self.expected_reliable
+                    || sequence - self.expected_reliable >= RECEIVE_WINDOW
+                {
+                    return payloads;
+                }
+
+                self.received.insert(sequence, message.payload);
+
+                while let Some(payload) = self.received.remove(&self.expected_reliable) {
+                    payloads.push(payload);
+                    self.expected_reliable += 1;
+                }
+            }
+            Delivery::Sequenced => {
+                if self
+                    .last_sequenced
+                    .map_or(true, |last| message.sequence > last)
+                {
+                    self.last_sequenced = Some(message.sequence);
+                    payloads.push(message.payload);
+                }
+            }
+        }
+
+        payloads
+    }
+
+    /// bases the resend timeout on the round trip time and its jitter, both in milliseconds, so
+    /// slow links don't get messages resent before an ack could possibly arrive
+    pub fn set_round_trip(&mut self, rtt: f64, jitter: f64) {
+        let timeout = Duration::from_secs_f64((rtt * 1.5 + jitter * 4.0).max(0.0) / 1000.0);
+        self.resend_timeout = timeout.max(MIN_RESEND_TIMEOUT).min(MAX_RESEND_TIMEOUT);
+    }
+
+    /// indicates reliable messages are still waiting to be acknowledged
+    pub fn has_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// packets to send this frame: timed out reliable messages and a bare ack if one is owed
+    pub fn flush(&mut self) -> Vec<Packet> {
+        let mut sequences = Vec::new();
+
+        for (sequence, pending) in self.pending.iter_mut() {
+            let backoff = 1 << pending.resends.min(MAX_RESEND_DOUBLINGS);
+            let timeout = (self.resend_timeout * backoff).min(MAX_RESEND_TIMEOUT);
+
+            if pending.last_sent.elapsed() >= timeout {
+                pending.last_sent = Instant::now();
+                pending.resends += 1;
+                sequences.push(*sequence);
+            }
+        }
+
+        let mut packets = Vec::new();
+
+        for sequence in sequences {
+            let payload = self.pending[&sequence].payload.clone();
+
+            packets.push(self.packet(Some(Message {
+                delivery: Delivery::Reliable,
+                sequence,
+                payload,
+            })));
+        }
+
+        if self.ack_required {
+            packets.push(self.packet(None));
+        }
+
+        packets
+    }
+
+    fn packet(&mut self, message: Option<Message>) -> Packet {
+        self.ack_required = false;
+
+        let mut ack_bits = 0;
+        for n in 0..ACK_BITS {
+            if self
+                .received
+                .contains_key(&(self.expected_reliable + 1 + n))
+            {
+                ack_bits |= 1 << n;
+            }
+        }
+
+        Packet {
+            ack: self.expected_reliable,
+            ack_bits,
+            message,
+        }
+    }
+
+    fn acknowledge(&mut self, ack: Sequence, ack_bits: u32) {
+        self.pending.retain(|sequence, _| {
+            if *sequence < ack {
+                return false;
+            }
+
+            let offset = *sequence - ack;
+            !(offset >= 1 && offset <= ACK_BITS && ack_bits & (1 << (offset - 1)) != 0)
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::transport::{
+        MemoryTransport, NetworkConditions, SimulatedTransport, Transport,
+    };
+
+    use std::thread;
+
+    fn payload(n: u8) -> Vec<u8> {
+        vec![n]
+    }
+
+    /// handles everything waiting on the transport, then sends what the channel has to send
+    fn pump(channel: &mut Channel, transport: &mut dyn Transport) -> Vec<Vec<u8>> {
+        let mut payloads = Vec::new();
+
+        while let Some(data) = transport.recv().unwrap() {
+            payloads.append(&mut channel.receive(bincode::deserialize(&data).unwrap()));
+        }
+
+        for packet in channel.flush() {
+            transport
+                .send(&bincode::serialize(&packet).unwrap())
+                .unwrap();
+        }
+
+        payloads
+    }
+
+    #[test]
+    fn acknowledged_messages_are_not_resent() {
+        let mut client = Channel::new();
+        let mut server = Channel::new();
+
+        let first = client.send(Delivery::Reliable, payload(0));
+        let second = client.send(Delivery::Reliable, payload(1));
+        assert_eq!(server.receive(first), vec![payload(0)]);
+        assert_eq!(server.receive(second), vec![payload(1)]);
+
+        let acks = server.flush();
+        assert_eq!(acks.len(), 1);
+        assert_eq!(acks[0].ack, 2);
+        assert_eq!(acks[0].ack_bits, 0);
+        assert!(acks[0].message.is_none());
+
+        client.receive(acks[0].clone());
+        thread::sleep(MIN_RESEND_TIMEOUT);
+        assert!(client.flush().is_empty());
+    }
+
+    #[test]
+    fn messages_received_out_of_order_are_acked_with_bits() {
+        let mut client = Channel::new();
+        let mut server = Channel::new();
+
+        let _lost = client.send(Delivery::Reliable, payload(0));
+        let second = client.send(Delivery::Reliable, payload(1));
+        let third = client.send(Delivery::Reliable, payload(2));
+
+        // held back until the first one arrives
+        assert!(server.receive(second).is_empty());
+        assert!(server.receive(third).is_empty());
+
+        let acks = server.flush();
+        assert_eq!(acks[0].ack, 0);
+        assert_eq!(acks[0].ack_bits, 0b11);
+
+        client.receive(acks[0].clone());
+        assert_eq!(client.pending.keys().collect::<Vec<_>>(), vec![&0]);
+    }
+
+    #[test]
+    fn lost_messages_are_resent_after_a_timeout() {
+        let mut client = Channel::new();
+        let mut server = Channel::new();
+
+        let _lost = client.send(Delivery::Reliable, payload(0));
+        let second = client.send(Delivery::Reliable, payload(1));
+        assert!(server.receive(second).is_empty());
+        client.receive(server.flush().remove(0));
+
+        assert!(client.flush().is_empty());
+        thread::sleep(MIN_RESEND_TIMEOUT);
+
+        let resent = client.flush();
+        assert_eq!(resent.len(), 1);
+        assert_eq!(resent[0].message.as_ref().unwrap().sequence, 0);
+        assert_eq!(
+            server.receive(resent[0].clone()),
+            vec![payload(0), payload(1)]
+        );
+    }
+
+    #[test]
+    fn resends_back_off() {
+        let mut client = Channel::new();
+        let _lost = client.send(Delivery::Reliable, payload(0));
+
+        thread::sleep(MIN_RESEND_TIMEOUT);
+        assert_eq!(client.flush().len(), 1);
+
+        // twice as long for the second resend
+        thread::sleep(MIN_RESEND_TIMEOUT);
+        assert!(client.flush().is_empty());
+        thread::sleep(MIN_RESEND_TIMEOUT);
+        assert_eq!(client.flush().len(), 1);
+    }
+
+    #[test]
+    fn resend_timeout_follows_the_round_trip() {
+        let mut channel = Channel::new();
+
+        channel.set_round_trip(200.0, 10.0);
+        assert!((channel.resend_timeout.as_secs_f64() - 0.34).abs() < 1e-6);
+
+        channel.set_round_trip(10.0, 0.0);
+        assert_eq!(channel.resend_timeout, MIN_RESEND_TIMEOUT);
+
+        channel.set_round_trip(5000.0, 0.0);
+        assert_eq!(channel.resend_timeout, MAX_RESEND_TIMEOUT);
+    }
+
+    #[test]
+    fn duplicated_messages_are_delivered_once() {
+        let mut client = Channel::new();
+        let mut server = Channel::new();
+
+        let packet = client.send(Delivery::Reliable, payload(0));
+        assert_eq!(server.receive(packet.clone()), vec![payload(0)]);
+        assert!(server.receive(packet).is_empty());
+    }
+
+    #[test]
+    fn sequenced_messages_older_than_the_last_one_are_dropped() {
+        let mut client = Channel::new();
+        let mut server = Channel::new();
+
+        let first = client.send(Delivery::Sequenced, payload(0));
+        let second = client.send(Delivery::Sequenced, payload(1));
+        let third = client.send(Delivery::Sequenced, payload(2));
+
+        assert_eq!(server.receive(second), vec![payload(1)]);
+        assert!(server.receive(first).is_empty());
+        assert_eq!(server.receive(third), vec![payload(2)]);
+        assert!(server.flush().is_empty());
+        assert!(client.pending.is_empty());
+    }
+
+    #[test]
+    fn reliable_messages_survive_a_lossy_network() {
+        let conditions = NetworkConditions {
+            latency: 0,
+            jitter: 5,
+            loss: 0.3,
+            duplication: 0.1,
+            reordering: 0.2,
+        };
+
+        let (client_end, server_end) = MemoryTransport::pair();
+        let mut client_transport = SimulatedTransport::new(Box::new(client_end), conditions);
+        let mut server_transport = SimulatedTransport::new(Box::new(server_end), conditions);
+        let mut client = Channel::new();
+        let mut server = Channel::new();
+
+        let sent: Vec<Vec<u8>> = (0..20).map(payload).collect();
+        for payload in sent.iter() {
+            let packet = client.send(Delivery::Reliable, payload.clone());
+            client_transport
+                .send(&bincode::serialize(&packet).unwrap())
+                .unwrap();
+        }
+
+        let mut received = Vec::new();
+        let started = Instant::now();
+
+        while (received.len() < sent.len() || !client.pending.is_empty())
+            && started.elapsed() < Duration::from_secs(10)
+        {
+            assert!(pump(&mut client, &mut client_transport).is_empty());
+            received.append(&mut pump(&mut server, &mut server_transport));
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        assert_eq!(received, sent);
+        assert!(client.pending.is_empty());
+    }
+}
@@ -0,0 +1,140 @@
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// longest message in characters, checked by the client and the server
+pub const MAX_MESSAGE_LENGTH: usize = 256;
+/// messages that can be sent in a row before the rate limit kicks in
+const RATE_LIMIT_BURST: f64 = 5.0;
+/// then one more message is allowed every interval
+const RATE_LIMIT_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ChatError {
+    TooLong,
+    RateLimited,
+}
+
+impl fmt::Display for ChatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ChatError::TooLong => write!(
+                f,
+                "messages are limited to {} characters",
+                MAX_MESSAGE_LENGTH
+            ),
+            ChatError::RateLimited => write!(f, "you are sending messages too fast"),
+        }
+    }
+}
+
+/// Token bucket allowing short bursts of messages.
+pub struct RateLimiter {
+    tokens: f64,
+    last_update: Instant,
+}
+
+impl RateLimiter {
+    pub fn new() -> Self {
+        Self {
+            tokens: RATE_LIMIT_BURST,
+            last_update: Instant::now(),
+        }
+    }
+
+    /// takes one token if there is one left
+    pub fn try_acquire(&mut self) -> bool {
+        let refill = self.last_update.elapsed().as_secs_f64() / RATE_LIMIT_INTERVAL.as_secs_f64();
+        self.tokens = (self.tokens + refill).min(RATE_LIMIT_BURST);
+        self.last_update = Instant::now();
+
+        if self.tokens < 1.0 {
+            return false;
+        }
+
+        self.tokens -= 1.0;
+        true
+    }
+}
+
+/// checks a message against the limits before it is sent or broadcast
+pub fn validate_message(text: &str, limiter: &mut RateLimiter) -> Result<(), ChatError> {
+    if text.chars().count() > MAX_MESSAGE_LENGTH {
+        return Err(ChatError::TooLong);
+    }
+
+    if !limiter.try_acquire() {
+        return Err(ChatError::RateLimited);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// pretends `duration` went by since the limiter was last used
+    fn wait(limiter: &mut RateLimiter, duration: Duration) {
+        limiter.last_update -= duration;
+    }
+
+    fn acquired(limiter: &mut RateLimiter, attempts: usize) -> usize {
+        (0..attempts).filter(|_| limiter.try_acquire()).count()
+    }
+
+    #[test]
+    fn a_burst_is_allowed_then_one_message_per_interval() {
+        let mut limiter = RateLimiter::new();
+        assert_eq!(acquired(&mut limiter, 10), 5);
+
+        wait(&mut limiter, RATE_LIMIT_INTERVAL / 2);
+        assert_eq!(acquired(&mut limiter, 1), 0);
+
+        wait(&mut limiter, RATE_LIMIT_INTERVAL);
+        assert_eq!(acquired(&mut limiter, 3), 1);
+    }
+
+    #[test]
+    fn the_refill_is_capped_at_the_burst() {
+        let mut limiter = RateLimiter::new();
+        acquired(&mut limiter, 5);
+
+        wait(&mut limiter, RATE_LIMIT_INTERVAL * 100);
+        assert_eq!(acquired(&mut limiter, 10), 5);
+    }
+
+    #[test]
+    fn the_length_is_counted_in_characters() {
+        let mut limiter = RateLimiter::new();
+        let longest = "é".repeat(MAX_MESSAGE_LENGTH);
+        assert!(longest.len() > MAX_MESSAGE_LENGTH);
+        assert_eq!(validate_message(&longest, &mut limiter), Ok(()));
+
+        let too_long = "a".repeat(MAX_MESSAGE_LENGTH + 1);
+        assert_eq!(
+            validate_message(&too_long, &mut limiter),
+            Err(ChatError::TooLong)
+        );
+    }
+
+    #[test]
+    fn rejected_messages_do_not_count_against_the_limit() {
+        let mut limiter = RateLimiter::new();
+        let too_long = "a".repeat(MAX_MESSAGE_LENGTH + 1);
+
+        for _ in 0..10 {
+            assert_eq!(
+                validate_message(&too_long, &mut limiter),
+                Err(ChatError::TooLong)
+            );
+        }
+
+        for _ in 0..5 {
+            assert_eq!(validate_message("hi", &mut limiter), Ok(()));
+        }
+        assert_eq!(
+            validate_message("hi", &mut limiter),
+            Err(ChatError::RateLimited)
+        );
+    }
+}
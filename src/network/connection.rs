@@ -0,0 +1,242 @@
+use crate::network::protocol::PROTOCOL_VERSION;
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(1);
+const CONNECTION_TIMEOUT: Duration = Duration::from_secs(5);
+/// longest time spent waiting for the server to acknowledge a disconnect
+const DISCONNECT_TIMEOUT: Duration = Duration::from_secs(1);
+/// delay before the first reconnection attempt, doubled after every failed attempt
+const RECONNECT_INITIAL_DELAY: Duration = Duration::from_secs(1);
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(30);
+const MAX_RECONNECT_ATTEMPTS: u32 = 10;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConnectionState {
+    Connecting,
+    Connected,
+    TimedOut,
+    /// the connection was lost and the attempt-th reconnection is scheduled
+    Reconnecting {
+        attempt: u32,
+    },
+    Rejected {
+        protocol_version: u32,
+    },
+    /// the transport stopped working, it can't be used anymore
+    Failed {
+        reason: String,
+    },
+    /// the disconnect was sent and is resent until the server acknowledges it
+    Disconnecting,
+    Disconnected,
+}
+
+impl ConnectionState {
+    /// indicates the connection is being established, established or being closed
+    pub fn is_active(&self) -> bool {
+        matches!(
+            self,
+            ConnectionState::Connecting
+                | ConnectionState::Connected
+                | ConnectionState::Reconnecting { .. }
+                | ConnectionState::Disconnecting
+        )
+    }
+}
+
+impl fmt::Display for ConnectionState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConnectionState::Connecting => write!(f, "connecting"),
+            ConnectionState::Connected => write!(f, "connected"),
+            ConnectionState::TimedOut => write!(f, "the server stopped responding"),
+            ConnectionState::Reconnecting { attempt } => {
+                write!(
+                    f,
+                    "reconnecting (attempt {}/{})",
+                    attempt, MAX_RECONNECT_ATTEMPTS
+                )
+            }
+            ConnectionState::Rejected { protocol_version } => write!(
+                f,
+                "the server speaks protocol version {} but this client speaks version {}",
+                protocol_version, PROTOCOL_VERSION
+            ),
+            ConnectionState::Failed { reason } => write!(f, "the connection failed: {}", reason),
+            ConnectionState::Disconnecting => write!(f, "disconnecting"),
+            ConnectionState::Disconnected => write!(f, "disconnected"),
+        }
+    }
+}
+
+pub struct Connection {
+    state: ConnectionState,
+    state_changed: bool,
+    changed_at: Instant,
+    last_received: Instant,
+    last_sent: Instant,
+}
+
+impl Connection {
+    pub fn new() -> Self {
+        Self {
+            state: ConnectionState::Connecting,
+            state_changed: false,
+            changed_at: Instant::now(),
+            last_received: Instant::now(),
+            last_sent: Instant::now(),
+        }
+    }
+
+    pub fn state(&self) -> &ConnectionState {
+        &self.state
+    }
+
+    pub fn set_state(&mut self, state: ConnectionState) {
+        if self.state != state {
+            self.state = state;
+            self.state_changed = true;
+            self.changed_at = Instant::now();
+        }
+    }
+
+    /// returns the new state once after every transition
+    pub fn take_state_change(&mut self) -> Option<ConnectionState> {
+        if self.state_changed {
+            self.state_changed = false;
+            Some(self.state.clone())
+        } else {
+            None
+        }
+    }
+
+    /// starts establishing the connection again, as if it was new
+    pub fn restart(&mut self) {
+        self.set_state(ConnectionState::Connecting);
+        self.last_received = Instant::now();
+        self.last_sent = Instant::now();
+    }
+
+    pub fn received(&mut self) {
+        self.last_received = Instant::now();
+    }
+
+    pub fn sent(&mut self) {
+        self.last_sent = Instant::now();
+    }
+
+    /// indicates nothing was sent for a while and the server should be reminded we're here
+    pub fn needs_heartbeat(&self) -> bool {
+        self.state == ConnectionState::Connected && self.last_sent.elapsed() >= HEARTBEAT_INTERVAL
+    }
+
+    /// to call every frame, marks the connection as timed out if the server went silent and
+    /// gives up on a disconnect the server never acknowledged
+    pub fn update(&mut self) {
+        let waiting = matches!(
+            self.state,
+            ConnectionState::Connecting | ConnectionState::Connected
+        );
+
+        if waiting && self.last_received.elapsed() >= CONNECTION_TIMEOUT {
+            self.set_state(ConnectionState::TimedOut);
+        }
+
+        if self.state == ConnectionState::Disconnecting
+            && self.changed_at.elapsed() >= DISCONNECT_TIMEOUT
+        {
+            self.set_state(ConnectionState::Disconnected);
+        }
+    }
+
+    /// pretends the server has been silent for long enough to time out
+    #[cfg(test)]
+    pub fn expire(&mut self) {
+        self.last_received -= CONNECTION_TIMEOUT;
+    }
+}
+
+/// Schedules reconnection attempts further and further apart until one succeeds or we give up.
+pub struct Backoff {
+    attempt: u32,
+    next_attempt: Instant,
+}
+
+impl Backoff {
+    pub fn new() -> Self {
+        Self {
+            attempt: 0,
+            next_attempt: Instant::now(),
+        }
+    }
+
+    /// schedules the next attempt and returns its number, `None` once out of attempts
+    pub fn next(&mut self) -> Option<u32> {
+        if self.attempt >= MAX_RECONNECT_ATTEMPTS {
+            return None;
+        }
+
+        let delay = (RECONNECT_INITIAL_DELAY * 2u32.pow(self.attempt)).min(RECONNECT_MAX_DELAY);
+        self.attempt += 1;
+        self.next_attempt = Instant::now() + delay;
+
+        Some(self.attempt)
+    }
+
+    pub fn is_due(&self) -> bool {
+        Instant::now() >= self.next_attempt
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn delay(backoff: &Backoff) -> Duration {
+        backoff
+            .next_attempt
+            .saturating_duration_since(Instant::now())
+    }
+
+    #[test]
+    fn reconnection_delays_double_up_to_the_cap() {
+        let mut backoff = Backoff::new();
+        let expected = [1, 2, 4, 8, 16, 30, 30];
+
+        for (attempt, seconds) in expected.iter().enumerate() {
+            assert_eq!(backoff.next(), Some(attempt as u32 + 1));
+
+            let expected = Duration::from_secs(*seconds);
+            assert!(delay(&backoff) <= expected);
+            assert!(delay(&backoff) > expected - Duration::from_millis(100));
+        }
+    }
+
+    #[test]
+    fn reconnection_is_given_up_after_the_last_attempt() {
+        let mut backoff = Backoff::new();
+        assert!(backoff.is_due());
+
+        for attempt in 1..=MAX_RECONNECT_ATTEMPTS {
+            assert_eq!(backoff.next(), Some(attempt));
+            assert!(!backoff.is_due());
+        }
+
+        assert_eq!(backoff.next(), None);
+        assert_eq!(backoff.next(), None);
+    }
+
+    #[test]
+    fn a_silent_server_times_out() {
+        let mut connection = Connection::new();
+        connection.set_state(ConnectionState::Connected);
+        connection.update();
+        assert_eq!(*connection.state(), ConnectionState::Connected);
+
+        connection.expire();
+        connection.update();
+        assert_eq!(*connection.state(), ConnectionState::TimedOut);
+    }
+}
@@ -0,0 +1,247 @@
+use crate::network::protocol::PROTOCOL_VERSION;
+use crate::network::RemoteInfo;
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use std::io::{self, ErrorKind};
+use std::net::{Ipv4Addr, SocketAddr, UdpSocket};
+use std::process;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// port servers listen on for discovery probes
+pub const DISCOVERY_PORT: u16 = 25566;
+/// tells our datagrams apart from anything else broadcast on the network
+const MAGIC: u32 = 0x5244_5343;
+const MAX_DATAGRAM_SIZE: usize = 1200;
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+#[derive(Serialize, Deserialize)]
+enum DiscoveryMessage {
+    Probe {
+        magic: u32,
+        nonce: u32,
+    },
+    Announce {
+        magic: u32,
+        nonce: u32,
+        /// tells the server apart when it answers from several addresses
+        server_id: u32,
+        name: String,
+        player_count: u32,
+        protocol_version: u32,
+        /// port the game is served on
+        port: u16,
+        /// no one else can join
+        full: bool,
+    },
+}
+
+/// what a server told about itself when it answered a discovery probe
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerDetails {
+    pub name: String,
+    pub player_count: u32,
+    pub protocol_version: u32,
+    pub full: bool,
+    pub ping: Duration,
+}
+
+impl ServerDetails {
+    pub fn is_compatible(&self) -> bool {
+        self.protocol_version == PROTOCOL_VERSION
+    }
+
+    pub fn is_joinable(&self) -> bool {
+        self.is_compatible() && !self.full
+    }
+}
+
+impl fmt::Display for ServerDetails {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} ({} players, {}ms",
+            self.name,
+            self.player_count,
+            self.ping.as_millis()
+        )?;
+
+        if !self.is_compatible() {
+            write!(f, ", protocol version {}", self.protocol_version)?;
+        }
+
+        if self.full {
+            write!(f, ", full")?;
+        }
+
+        write!(f, ")")
+    }
+}
+
+/// Broadcasts a probe on the local network, and on loopback for servers running on this
+/// machine, then collects the answers for `timeout`. Servers are sorted by ping.
+pub fn discover(timeout: Duration) -> io::Result<Vec<RemoteInfo>> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+    socket.set_broadcast(true)?;
+    socket.set_nonblocking(true)?;
+
+    let nonce = random();
+    let probe = bincode::serialize(&DiscoveryMessage::Probe {
+        magic: MAGIC,
+        nonce,
+    })
+    .unwrap();
+
+    let sent = Instant::now();
+    for address in [Ipv4Addr::BROADCAST, Ipv4Addr::LOCALHOST].iter() {
+        if let Err(err) = socket.send_to(&probe, (*address, DISCOVERY_PORT)) {
+            warn!("<discovery> could not send a probe to {}: {}", address, err);
+        }
+    }
+
+    let mut servers: Vec<RemoteInfo> = Vec::new();
+    let mut found = HashSet::new();
+    while sent.elapsed() < timeout {
+        let mut data = [0; MAX_DATAGRAM_SIZE];
+        let (size, address) = match socket.recv_from(&mut data) {
+            Ok(result) => result,
+            Err(err) if err.kind() == ErrorKind::WouldBlock => {
+                thread::sleep(POLL_INTERVAL);
+                continue;
+            }
+            Err(err) => return Err(err),
+        };
+
+        let (server_id, name, player_count, protocol_version, port, full) =
+            match bincode::deserialize(&data[..size]) {
+                Ok(DiscoveryMessage::Announce {
+                    magic,
+                    nonce: reply,
+                    server_id,
+                    name,
+                    player_count,
+                    protocol_version,
+                    port,
+                    full,
+                }) if magic == MAGIC && reply == nonce => {
+                    (server_id, name, player_count, protocol_version, port, full)
+                }
+                _ => continue,
+            };
+
+        // a server on this machine answers both the broadcast and the loopback probe, from
+        // different addresses
+        if !found.insert(server_id) {
+            continue;
+        }
+
+        let mut info = RemoteInfo::new(address.ip().to_string(), port);
+        info.details = Some(ServerDetails {
+            name,
+            player_count,
+            protocol_version,
+            full,
+            ping: sent.elapsed(),
+        });
+        servers.push(info);
+    }
+
+    servers.sort_by_key(|server| server.details.as_ref().map(|details| details.ping));
+    Ok(servers)
+}
+
+/// Answers discovery probes on behalf of a server.
+pub struct DiscoveryResponder {
+    socket: UdpSocket,
+    id: u32,
+    name: String,
+    port: u16,
+}
+
+impl DiscoveryResponder {
+    /// `port` is where the game itself is served
+    pub fn bind(name: String, port: u16) -> io::Result<Self> {
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, DISCOVERY_PORT))?;
+        socket.set_nonblocking(true)?;
+
+        Ok(Self {
+            socket,
+            id: random(),
+            name,
+            port,
+        })
+    }
+
+    /// answers every probe received since the last call
+    pub fn process(&mut self, player_count: u32, full: bool) {
+        loop {
+            let mut data = [0; MAX_DATAGRAM_SIZE];
+            let (size, address): (usize, SocketAddr) = match self.socket.recv_from(&mut data) {
+                Ok(result) => result,
+                Err(_) => break,
+            };
+
+            let nonce = match bincode::deserialize(&data[..size]) {
+                Ok(DiscoveryMessage::Probe { magic, nonce }) if magic == MAGIC => nonce,
+                _ => continue,
+            };
+
+            let announce = bincode::serialize(&DiscoveryMessage::Announce {
+                magic: MAGIC,
+                nonce,
+                server_id: self.id,
+                name: self.name.clone(),
+                player_count,
+                protocol_version: PROTOCOL_VERSION,
+                port: self.port,
+                full,
+            })
+            .unwrap();
+
+            if let Err(err) = self.socket.send_to(&announce, address) {
+                warn!("<discovery> could not answer {}: {}", address, err);
+            }
+        }
+    }
+}
+
+/// good enough to tell probes and servers apart
+fn random() -> u32 {
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.subsec_nanos());
+
+    time ^ process::id().rotate_left(16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn local_responder() {
+        let mut responder = DiscoveryResponder::bind(String::from("test"), 4242).unwrap();
+        let answering = thread::spawn(move || {
+            let started = Instant::now();
+            while started.elapsed() < Duration::from_millis(500) {
+                responder.process(3, true);
+                thread::sleep(POLL_INTERVAL);
+            }
+        });
+
+        let servers = discover(Duration::from_millis(300)).unwrap();
+        answering.join().unwrap();
+
+        // found once even when the broadcast reaches it as well
+        assert_eq!(servers.len(), 1);
+        assert_eq!(servers[0].port, 4242);
+
+        let details = servers[0].details.as_ref().unwrap();
+        assert_eq!(details.name, "test");
+        assert_eq!(details.player_count, 3);
+        assert!(details.is_compatible());
+        assert!(!details.is_joinable());
+    }
+}
@@ -0,0 +1,267 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// largest datagram we send, small enough to fit in the path MTU of most networks
+pub const MAX_DATAGRAM_SIZE: usize = 1200;
+/// room left for the datagram enum tag, fragment header and length prefix
+const DATAGRAM_OVERHEAD: usize = 32;
+const FRAGMENT_SIZE: usize = MAX_DATAGRAM_SIZE - DATAGRAM_OVERHEAD;
+const MAX_FRAGMENTS: u16 = 1024;
+const MAX_PARTIAL_MESSAGES: usize = 64;
+const FRAGMENT_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Datagram {
+    Whole(Vec<u8>),
+    Fragment {
+        id: u32,
+        index: u16,
+        count: u16,
+        data: Vec<u8>,
+    },
+}
+
+pub struct Fragmenter {
+    next_id: u32,
+}
+
+impl Fragmenter {
+    pub fn new() -> Self {
+        Self { next_id: 0 }
+    }
+
+    /// splits a message into datagrams no larger than `MAX_DATAGRAM_SIZE` once serialized
+    pub fn split(&mut self, message: Vec<u8>) -> Vec<Datagram> {
+        if message.len() <= FRAGMENT_SIZE {
+            return vec![Datagram::Whole(message)];
+        }
+
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1);
+
+        let chunks = message.chunks(FRAGMENT_SIZE);
+        let count = chunks.len();
+
+        if count > MAX_FRAGMENTS as usize {
+            error!(
+                "<network> message of {} bytes is too large to be sent",
+                message.len()
+            );
+            return Vec::new();
+        }
+
+        chunks
+            .enumerate()
+            .map(|(index, data)| Datagram::Fragment {
+                id,
+                index: index as u16,
+                count: count as u16,
+                data: data.to_vec(),
+            })
+            .collect()
+    }
+}
+
+struct PartialMessage {
+    fragments: Vec<Option<Vec<u8>>>,
+    missing: usize,
+    created: Instant,
+}
+
+pub struct Reassembler {
+    partials: HashMap<u32, PartialMessage>,
+}
+
+impl Reassembler {
+    pub fn new() -> Self {
+        Self {
+            partials: HashMap::new(),
+        }
+    }
+
+    /// returns the complete message once its last missing fragment arrives
+    pub fn insert(&mut self, datagram: Datagram) -> Option<Vec<u8>> {
+        let (id, index, count, data) = match datagram {
+            Datagram::Whole(message) => return Some(message),
+            Datagram::Fragment {
+                id,
+                index,
+                count,
+                data,
+            } => (id, index as usize, count as usize, data),
+        };
+
+        if count == 0 || count > MAX_FRAGMENTS as usize || index >= count {
+            return None;
+        }
+
+        // resent messages get new ids, the oldest partial message is the likeliest orphan
+        if !self.partials.contains_key(&id) && self.partials.len() >= MAX_PARTIAL_MESSAGES {
+            let oldest = self
+                .partials
+                .iter()
+                .min_by_key(|(_, partial)| partial.created)
+                .map(|(id, _)| *id)?;
+
+            debug!("<network> too many partial messages, dropping the oldest one");
+            self.partials.remove(&oldest);
+        }
+
+        let partial = self.partials.entry(id).or_insert_with(|| PartialMessage {
+            fragments: vec![None; count],
+            missing: count,
+            created: Instant::now(),
+        });
+
+        if partial.fragments.len() != count || partial.fragments[index].is_some() {
+            return None;
+        }
+
+        partial.fragments[index] = Some(data);
+        partial.missing -= 1;
+
+        if partial.missing > 0 {
+            return None;
+        }
+
+        let partial = self.partials.remove(&id)?;
+        Some(partial.fragments.into_iter().flatten().flatten().collect())
+    }
+
+    /// drops messages that have been waiting on a lost fragment for too long
+    pub fn expire(&mut self) {
+        self.partials
+            .retain(|_, partial| partial.created.elapsed() < FRAGMENT_TIMEOUT);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(size: usize) -> Vec<u8> {
+        (0..size).map(|n| (n % 251) as u8).collect()
+    }
+
+    fn fragment(id: u32, index: u16, count: u16) -> Datagram {
+        Datagram::Fragment {
+            id,
+            index,
+            count,
+            data: vec![index as u8],
+        }
+    }
+
+    #[test]
+    fn small_messages_are_sent_whole() {
+        let datagrams = Fragmenter::new().split(message(FRAGMENT_SIZE));
+
+        assert_eq!(datagrams.len(), 1);
+        assert!(matches!(datagrams[0], Datagram::Whole(_)));
+    }
+
+    #[test]
+    fn split_messages_are_reassembled() {
+        let sent = message(FRAGMENT_SIZE * 3 + 10);
+        let datagrams = Fragmenter::new().split(sent.clone());
+        assert_eq!(datagrams.len(), 4);
+
+        for datagram in datagrams.iter() {
+            assert!(bincode::serialize(datagram).unwrap().len() <= MAX_DATAGRAM_SIZE);
+        }
+
+        let mut reassembler = Reassembler::new();
+        let last = datagrams.len() - 1;
+        for (index, datagram) in datagrams.into_iter().enumerate() {
+            let received = reassembler.insert(datagram);
+
+            if index == last {
+                assert_eq!(received, Some(sent.clone()));
+            } else {
+                assert!(received.is_none());
+            }
+        }
+
+        assert!(reassembler.partials.is_empty());
+    }
+
+    #[test]
+    fn fragments_can_arrive_in_any_order() {
+        let sent = message(FRAGMENT_SIZE * 2 + 1);
+        let mut datagrams = Fragmenter::new().split(sent.clone());
+        datagrams.swap(0, 2);
+
+        let mut reassembler = Reassembler::new();
+        assert!(reassembler.insert(datagrams[0].clone()).is_none());
+        assert!(reassembler.insert(datagrams[1].clone()).is_none());
+        assert_eq!(reassembler.insert(datagrams[2].clone()), Some(sent));
+    }
+
+    #[test]
+    fn duplicate_fragments_are_ignored() {
+        let mut reassembler = Reassembler::new();
+
+        assert!(reassembler.insert(fragment(7, 0, 2)).is_none());
+        assert!(reassembler.insert(fragment(7, 0, 2)).is_none());
+        assert_eq!(reassembler.partials[&7].missing, 1);
+        assert_eq!(reassembler.insert(fragment(7, 1, 2)), Some(vec![0, 1]));
+    }
+
+    #[test]
+    fn fragments_with_another_count_are_ignored() {
+        let mut reassembler = Reassembler::new();
+
+        assert!(reassembler.insert(fragment(7, 0, 2)).is_none());
+        assert!(reassembler.insert(fragment(7, 1, 3)).is_none());
+        assert!(reassembler.insert(fragment(7, 2, 2)).is_none());
+        assert_eq!(reassembler.partials[&7].missing, 1);
+        assert_eq!(reassembler.insert(fragment(7, 1, 2)), Some(vec![0, 1]));
+    }
+
+    #[test]
+    fn partial_messages_expire() {
+        let mut reassembler = Reassembler::new();
+        reassembler.insert(fragment(1, 0, 2));
+        reassembler.insert(fragment(2, 0, 2));
+
+        reassembler.partials.get_mut(&1).unwrap().created -= FRAGMENT_TIMEOUT;
+        reassembler.expire();
+
+        assert!(!reassembler.partials.contains_key(&1));
+        assert!(reassembler.partials.contains_key(&2));
+    }
+
+    #[test]
+    fn too_many_fragments_are_rejected() {
+        let datagrams =
+            Fragmenter::new().split(message(FRAGMENT_SIZE * MAX_FRAGMENTS as usize + 1));
+        assert!(datagrams.is_empty());
+
+        let mut reassembler = Reassembler::new();
+        assert!(reassembler
+            .insert(fragment(1, 0, MAX_FRAGMENTS + 1))
+            .is_none());
+        assert!(reassembler.partials.is_empty());
+    }
+
+    #[test]
+    fn the_oldest_partial_message_makes_room() {
+        let mut reassembler = Reassembler::new();
+        let now = Instant::now();
+
+        for id in 0..MAX_PARTIAL_MESSAGES as u32 {
+            reassembler.insert(fragment(id, 0, 2));
+            reassembler.partials.get_mut(&id).unwrap().created =
+                now - Duration::from_millis(1000 - id as u64);
+        }
+
+        let id = MAX_PARTIAL_MESSAGES as u32;
+        assert!(reassembler.insert(fragment(id, 0, 2)).is_none());
+
+        assert_eq!(reassembler.partials.len(), MAX_PARTIAL_MESSAGES);
+        assert!(!reassembler.partials.contains_key(&0));
+        assert!(reassembler.partials.contains_key(&1));
+        assert!(reassembler.partials.contains_key(&id));
+    }
+}
@@ -0,0 +1,557 @@
+use crate::network::block::{BlockAction, BlockCoordinate, AIR};
+use crate::network::channel::Channel;
+use crate::network::chat::{validate_message, RateLimiter};
+use crate::network::discovery::DiscoveryResponder;
+use crate::network::fragment::{Datagram, Fragmenter, Reassembler};
+use crate::network::movement::MovementBudget;
+use crate::network::packet::{Delivery, Packet};
+use crate::network::protocol::{
+    ClientMessage, GameMessage, LoginRejection, PlayerId, ServerMessage, DAY_LENGTH,
+    PROTOCOL_VERSION, TICKS_PER_SECOND,
+};
+use crate::network::quantize::{PositionEncoder, QuantizedPosition};
+use crate::network::transport::{MemoryTransport, Transport, UdpTransport};
+use crate::network::RemoteInfo;
+
+use core::events::{ClientEvent, ServerEvent};
+use core::world::{World, WorldCoordinate};
+use math::vector::Vector3;
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hasher};
+use std::io;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const TICK: Duration = Duration::from_millis(5);
+const BOT_ID: PlayerId = 1;
+const BOT_NAME: &str = "bot";
+/// id given to the client once logged in
+const CLIENT_ID: PlayerId = 2;
+const MAX_USERNAME_LENGTH: usize = 16;
+const BOT_UPDATE_INTERVAL: Duration = Duration::from_millis(50);
+const BOT_RADIUS: f32 = 8.0;
+/// the bot leaves and joins back on this interval so connections are exercised too
+const BOT_PRESENCE_INTERVAL: Duration = Duration::from_secs(15);
+const TIME_SYNC_INTERVAL: Duration = Duration::from_secs(1);
+const LATENCY_REPORT_INTERVAL: Duration = Duration::from_secs(2);
+/// the day starts in the morning
+const DAY_START: u64 = DAY_LENGTH / 4;
+
+/// Stand-in for the server that speaks the same protocol over any transport. It handles
+/// the connection itself and lets the caller script what the other players do.
+pub struct MockServer {
+    transport: Box<dyn Transport>,
+    connected: bool,
+    channel: Channel,
+    fragmenter: Fragmenter,
+    reassembler: Reassembler,
+    started: Instant,
+    responder: Option<DiscoveryResponder>,
+
+    seed: u32,
+    world: World,
+    players: Vec<PlayerId>,
+    names: HashMap<PlayerId, String>,
+    /// session token by username
+    sessions: HashMap<String, String>,
+    /// name the client logged in with
+    username: Option<String>,
+    chat_limiter: RateLimiter,
+    encoders: HashMap<PlayerId, PositionEncoder>,
+    position: WorldCoordinate,
+    movement_budget: MovementBudget,
+    last_input: Option<u32>,
+}
+
+impl MockServer {
+    /// server on a UDP socket, along with the info to reach it from this machine
+    pub fn bind(seed: u32) -> io::Result<(Self, RemoteInfo)> {
+        let transport = UdpTransport::listen("0.0.0.0:0")?;
+        let address = transport.local_addr()?;
+        let info = RemoteInfo::new(String::from("127.0.0.1"), address.port());
+
+        Ok((Self::new(Box::new(transport), seed), info))
+    }
+
+    /// server reached through an in-process pipe, along with the info to reach it
+    pub fn memory(seed: u32) -> (Self, RemoteInfo) {
+        let (client, server) = MemoryTransport::pair();
+
+        (
+            Self::new(Box::new(server), seed),
+            RemoteInfo::memory(client),
+        )
+    }
+
+    pub fn new(transport: Box<dyn Transport>, seed: u32) -> Self {
+        Self {
+            transport,
+            connected: false,
+            channel: Channel::new(),
+            fragmenter: Fragmenter::new(),
+            reassembler: Reassembler::new(),
+            started: Instant::now(),
+            responder: None,
+
+            seed,
+            world: World::from_seed(seed),
+            players: Vec::new(),
+            names: HashMap::new(),
+            sessions: HashMap::new(),
+            username: None,
+            chat_limiter: RateLimiter::new(),
+            encoders: HashMap::new(),
+            position: WorldCoordinate {
+                x: 0.0,
+                y: 70.0,
+                z: 0.0,
+            },
+            movement_budget: MovementBudget::new(),
+            last_input: None,
+        }
+    }
+
+    /// answers LAN discovery probes, `port` is the one the server was bound to
+    pub fn announce(&mut self, name: String, port: u16) -> io::Result<()> {
+        self.responder = Some(DiscoveryResponder::bind(name, port)?);
+        Ok(())
+    }
+
+    /// runs the server forever with a bot walking in circles, for offline multiplayer
+    pub fn run(mut self) {
+        let mut last_bot_update = Instant::now();
+        let mut last_bot_presence = Instant::now();
+        let mut last_time_sync = Instant::now();
+        let mut last_latency_report = Instant::now();
+
+        // the client learns about the bot from the server info it gets once connected
+        self.players.push(BOT_ID);
+        self.names.insert(BOT_ID, String::from(BOT_NAME));
+
+        loop {
+            self.process();
+
+            // a single client is served, the one started along with the server
+            let player_count = self.players.len() as u32 + self.connected as u32;
+            if let Some(responder) = self.responder.as_mut() {
+                responder.process(player_count, self.connected);
+            }
+
+            if !self.connected {
+                thread::sleep(TICK);
+                continue;
+            }
+
+            if last_time_sync.elapsed() >= TIME_SYNC_INTERVAL {
+                self.send_world_time();
+                last_time_sync = Instant::now();
+            }
+
+            if last_latency_report.elapsed() >= LATENCY_REPORT_INTERVAL {
+                self.send_latencies();
+                last_latency_report = Instant::now();
+            }
+
+            if last_bot_presence.elapsed() >= BOT_PRESENCE_INTERVAL {
+                if self.players.contains(&BOT_ID) {
+                    self.disconnect_player(BOT_ID);
+                } else {
+                    self.connect_player(BOT_ID, String::from(BOT_NAME));
+                }
+
+                last_bot_presence = Instant::now();
+            }
+
+            if self.players.contains(&BOT_ID) && last_bot_update.elapsed() >= BOT_UPDATE_INTERVAL {
+                let time = self.started.elapsed().as_secs_f32();
+                let angle = time * 0.5;
+
+                // facing where it walks while nodding its head
+                self.move_player(
+                    BOT_ID,
+                    WorldCoordinate {
+                        x: angle.cos() * BOT_RADIUS,
+                        y: 70.0,
+                        z: angle.sin() * BOT_RADIUS,
+                    },
+                    Vector3 {
+                        x: time.sin() * 30.0,
+                        y: -angle.to_degrees(),
+                        z: 0.0,
+                    },
+                );
+
+                last_bot_update = Instant::now();
+            }
+
+            thread::sleep(TICK);
+        }
+    }
+
+    pub fn connect_player(&mut self, id: PlayerId, name: String) {
+        self.players.push(id);
+        self.names.insert(id, name.clone());
+        self.send_event(ServerEvent::PlayerConnected { id });
+        self.send_game_message(Delivery::Reliable, GameMessage::PlayerName { id, name });
+    }
+
+    pub fn disconnect_player(&mut self, id: PlayerId) {
+        self.players.retain(|player| *player != id);
+        self.names.remove(&id);
+        self.encoders.remove(&id);
+        self.send_event(ServerEvent::PlayerDisconnected { id });
+    }
+
+    /// sends the new position and euler angles of a player unless the client already has them
+    pub fn move_player(&mut self, id: PlayerId, position: WorldCoordinate, euler_angles: Vector3) {
+        let timestamp = self.started.elapsed().as_millis() as u64;
+        let encoded = self
+            .encoders
+            .entry(id)
+            .or_insert_with(PositionEncoder::new)
+            .encode(position, euler_angles);
+
+        if let Some((sequence, position, rotation)) = encoded {
+            self.send_game_message(
+                Delivery::Sequenced,
+                GameMessage::PlayerMoved {
+                    id,
+                    sequence,
+                    timestamp,
+                    position,
+                    rotation,
+                },
+            );
+        }
+    }
+
+    pub fn send_world_time(&mut self) {
+        let tick = (self.started.elapsed().as_secs_f64() * TICKS_PER_SECOND) as u64;
+        let day_time = ((tick + DAY_START) % DAY_LENGTH) as u32;
+
+        self.send_game_message(
+            Delivery::Sequenced,
+            GameMessage::WorldTime { tick, day_time },
+        );
+    }
+
+    /// the client is reached without going through a real network, the bot pretends to be far
+    pub fn send_latencies(&mut self) {
+        let time = self.started.elapsed().as_secs_f32();
+        // the client is left out, it measures its own round trip
+        let latencies: Vec<(PlayerId, u16)> = self
+            .players
+            .iter()
+            .map(|id| (*id, (150.0 + (time * 0.1).sin() * 120.0) as u16))
+            .collect();
+
+        self.send_game_message(
+            Delivery::Sequenced,
+            GameMessage::PlayerLatencies { latencies },
+        );
+    }
+
+    pub fn send_event(&mut self, event: ServerEvent) {
+        self.send_game_message(Delivery::Reliable, GameMessage::Event(event));
+    }
+
+    /// answers the connection messages and returns everything the client sent
+    pub fn process(&mut self) -> Vec<ClientMessage> {
+        let mut messages = Vec::new();
+
+        loop {
+            let data = match self.transport.recv() {
+                Ok(Some(data)) => data,
+                Ok(None) => break,
+                Err(err) => {
+                    // the client is gone, nothing is sent until it comes back
+                    if self.connected {
+                        warn!("<mock> could not receive: {}", err);
+                        self.connected = false;
+                    }
+
+                    break;
+                }
+            };
+
+            let packet: Option<Packet> = bincode::deserialize::<Datagram>(&data)
+                .ok()
+                .and_then(|datagram| self.reassembler.insert(datagram))
+                .and_then(|buffer| bincode::deserialize(&buffer).ok());
+
+            let packet = match packet {
+                Some(packet) => packet,
+                None => continue,
+            };
+
+            self.connected = true;
+
+            for payload in self.channel.receive(packet) {
+                if let Ok(message) = bincode::deserialize(&payload) {
+                    self.handle_message(&message);
+                    messages.push(message);
+                }
+            }
+        }
+
+        self.reassembler.expire();
+
+        for packet in self.channel.flush() {
+            self.send_packet(&packet);
+        }
+
+        messages
+    }
+
+    fn handle_message(&mut self, message: &ClientMessage) {
+        match message {
+            ClientMessage::Handshake { protocol_version } => {
+                if *protocol_version == PROTOCOL_VERSION {
+                    self.send_message(Delivery::Reliable, ServerMessage::HandshakeAccepted);
+                } else {
+                    self.send_message(
+                        Delivery::Reliable,
+                        ServerMessage::HandshakeRejected {
+                            protocol_version: PROTOCOL_VERSION,
+                        },
+                    );
+                }
+            }
+            ClientMessage::Heartbeat => {
+                self.send_message(Delivery::Sequenced, ServerMessage::Heartbeat)
+            }
+            ClientMessage::Ping { id } => {
+                self.send_message(Delivery::Sequenced, ServerMessage::Pong { id: *id })
+            }
+            ClientMessage::Login { username, token } => {
+                let message = match self.login(username, token.as_ref()) {
+                    Ok(token) => {
+                        self.username = Some(username.clone());
+                        GameMessage::LoginAccepted {
+                            id: CLIENT_ID,
+                            token,
+                        }
+                    }
+                    Err(reason) => GameMessage::LoginRejected { reason },
+                };
+
+                self.send_game_message(Delivery::Reliable, message);
+            }
+            ClientMessage::Event(ClientEvent::PlayerConnect) => {
+                self.send_event(ServerEvent::ServerInfo {
+                    seed: self.seed,
+                    player_ids: self.players.clone(),
+                });
+                self.send_world_time();
+
+                for (id, name) in self.names.clone() {
+                    self.send_game_message(
+                        Delivery::Reliable,
+                        GameMessage::PlayerName { id, name },
+                    );
+                }
+            }
+            ClientMessage::Event(_) => (),
+            ClientMessage::PlayerInput {
+                first_sequence,
+                inputs,
+            } => {
+                let mut sequence = None;
+                self.movement_budget.update();
+
+                for (index, input) in inputs.iter().enumerate() {
+                    let mut input = input.input(first_sequence.wrapping_add(index as u32));
+
+                    if self.last_input.map_or(true, |last| input.sequence > last) {
+                        input.time_delta = self.movement_budget.spend(input.time_delta);
+                        self.position = input.apply(self.position);
+                        self.last_input = Some(input.sequence);
+                        sequence = Some(input.sequence);
+                    }
+                }
+
+                if let Some(sequence) = sequence {
+                    // the authoritative position lives on the grid the client receives
+                    let position = QuantizedPosition::new(self.position);
+                    self.position = position.position();
+
+                    self.send_game_message(
+                        Delivery::Sequenced,
+                        GameMessage::PlayerState { sequence, position },
+                    );
+                }
+            }
+            // a real server relays it to the other players, there are none here
+            ClientMessage::PlayerLook { .. } => (),
+            ClientMessage::AcknowledgeMoves { moves } => {
+                for (id, sequence) in moves.iter() {
+                    if let Some(encoder) = self.encoders.get_mut(id) {
+                        encoder.acknowledge(*sequence);
+                    }
+                }
+            }
+            ClientMessage::EditBlock {
+                edit,
+                coords,
+                action,
+            } => {
+                let accepted = self.edit_block(*coords, *action);
+
+                self.send_game_message(
+                    Delivery::Reliable,
+                    GameMessage::BlockEditResult {
+                        edit: *edit,
+                        accepted,
+                    },
+                );
+            }
+            ClientMessage::Chat { text } => {
+                // the client is the only one listening, it gets its own message back
+                let message = match validate_message(text, &mut self.chat_limiter) {
+                    Ok(()) => GameMessage::Chat {
+                        name: self.username.clone(),
+                        text: text.clone(),
+                    },
+                    Err(err) => GameMessage::Chat {
+                        name: None,
+                        text: err.to_string(),
+                    },
+                };
+
+                self.send_game_message(Delivery::Reliable, message);
+            }
+            ClientMessage::RequestChunks { coords } => {
+                for coords in coords.iter() {
+                    let position = WorldCoordinate {
+                        x: coords.abs().x,
+                        y: 0.0,
+                        z: coords.abs().y,
+                    };
+                    self.world.load_around(vec![position]);
+
+                    if let Some(chunk) = self.world.chunks.get(coords) {
+                        let chunk = chunk.clone();
+                        self.send_game_message(
+                            Delivery::Reliable,
+                            GameMessage::ChunkData { chunk },
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    /// session token for the user, an existing session can only be resumed with its token
+    fn login(&mut self, username: &str, token: Option<&String>) -> Result<String, LoginRejection> {
+        let valid = !username.is_empty()
+            && username.chars().count() <= MAX_USERNAME_LENGTH
+            && username.chars().all(|c| c.is_alphanumeric() || c == '_');
+
+        if !valid {
+            return Err(LoginRejection::InvalidUsername);
+        }
+
+        if self.names.values().any(|name| name == username) {
+            return Err(LoginRejection::AlreadyPlaying);
+        }
+
+        if let Some(session) = self.sessions.get(username) {
+            return if token == Some(session) {
+                Ok(session.clone())
+            } else {
+                Err(LoginRejection::InvalidToken)
+            };
+        }
+
+        let random = RandomState::new();
+        let token = format!(
+            "{:016x}{:016x}",
+            random.build_hasher().finish(),
+            RandomState::new().build_hasher().finish()
+        );
+
+        self.sessions.insert(String::from(username), token.clone());
+        Ok(token)
+    }
+
+    /// blocks can only be broken if there is one and placed where there is none
+    fn edit_block(&mut self, coords: BlockCoordinate, action: BlockAction) -> bool {
+        let current = match coords.block(&self.world) {
+            Some(block) => block,
+            None => return false,
+        };
+
+        let is_air = current.id == AIR.id;
+        let valid = match action {
+            BlockAction::Break => !is_air,
+            BlockAction::Place(_) => is_air,
+        };
+
+        if !valid {
+            return false;
+        }
+
+        let block = action.block();
+        coords.set_block(&mut self.world, block);
+        self.send_game_message(
+            Delivery::Reliable,
+            GameMessage::BlockChanged { coords, block },
+        );
+
+        true
+    }
+
+    fn send_game_message(&mut self, delivery: Delivery, message: GameMessage) {
+        self.send_message(delivery, ServerMessage::Game(message));
+    }
+
+    fn send_message(&mut self, delivery: Delivery, message: ServerMessage) {
+        let payload = bincode::serialize(&message).unwrap();
+        let packet = self.channel.send(delivery, payload);
+
+        self.send_packet(&packet);
+    }
+
+    fn send_packet(&mut self, packet: &Packet) {
+        if !self.connected {
+            return;
+        }
+
+        let buffer = bincode::serialize(packet).unwrap();
+
+        for datagram in self.fragmenter.split(buffer) {
+            let buffer = bincode::serialize(&datagram).unwrap();
+            let _ = self.transport.send(&buffer);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::movement::PlayerInput;
+    use crate::network::quantize::QuantizedInput;
+
+    #[test]
+    fn inputs_cant_move_faster_than_real_time() {
+        let (mut server, _) = MockServer::memory(0);
+        let start = server.position;
+        let started = Instant::now();
+
+        let input = QuantizedInput::new(&PlayerInput {
+            z: 1.0,
+            time_delta: 6.0,
+            ..Default::default()
+        });
+        server.handle_message(&ClientMessage::PlayerInput {
+            first_sequence: 0,
+            inputs: vec![input; 32],
+        });
+
+        // 20 blocks per second at most
+        let moved = (server.position.z - start.z) as f64;
+        assert!(moved <= started.elapsed().as_secs_f64() * 20.0 + 0.01);
+        assert_eq!(server.last_input, Some(31));
+    }
+}
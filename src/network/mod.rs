@@ -1,5 +1,32 @@
+mod block;
+mod channel;
+mod chat;
+mod connection;
+mod discovery;
+mod fragment;
+mod mock_server;
+mod movement;
 mod network_handler;
+mod packet;
+mod protocol;
+mod quantize;
 mod remote_info;
+mod stats;
+mod transport;
 
+pub use self::block::{BlockAction, BlockCoordinate, AIR};
+pub use self::chat::{validate_message, ChatError, RateLimiter, MAX_MESSAGE_LENGTH};
+pub use self::connection::ConnectionState;
+pub use self::discovery::discover;
+pub use self::mock_server::MockServer;
+pub use self::movement::PlayerInput;
 pub use self::network_handler::NetworkHandler;
+pub use self::protocol::{
+    GameMessage, LoginRejection, PlayerId, DAY_LENGTH, PROTOCOL_VERSION, TICKS_PER_SECOND,
+};
+pub use self::quantize::PositionDecoder;
 pub use self::remote_info::RemoteInfo;
+pub use self::stats::NetworkStats;
+pub use self::transport::NetworkConditions;
+#[cfg(feature = "remote")]
+pub use self::transport::TransportType;
@@ -0,0 +1,124 @@
+use crate::network::quantize::QuantizedInput;
+
+use core::world::WorldCoordinate;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+const SPEED: f64 = 20.0;
+/// longest frame simulated in one input, in seconds. Longer frames are cut short on both sides
+/// so a single input can't move the player across the world.
+pub const MAX_INPUT_TIME_DELTA: f32 = 0.1;
+/// simulated time a client can get ahead of the wall clock, in seconds
+const MAX_MOVEMENT_SLACK: f64 = 0.5;
+
+/// movement requested by the player for a single frame
+#[derive(Debug, Copy, Clone, Default, Serialize, Deserialize)]
+pub struct PlayerInput {
+    pub sequence: u32,
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    /// camera yaw in degrees
+    pub yaw: f32,
+    pub time_delta: f32,
+}
+
+impl PlayerInput {
+    /// rounds the input like it is sent to the server, so both simulate the exact same movement
+    pub fn quantized(&self) -> Self {
+        QuantizedInput::new(self).input(self.sequence)
+    }
+
+    /// an idle input does not move the player and is not worth sending
+    pub fn is_idle(&self) -> bool {
+        self.x == 0.0 && self.y == 0.0 && self.z == 0.0
+    }
+
+    /// moves a position according to this input, the server runs the same simulation
+    pub fn apply(&self, position: WorldCoordinate) -> WorldCoordinate {
+        let angle = self.yaw.to_radians();
+
+        let delta = WorldCoordinate {
+            x: self.x * angle.cos() + self.z * angle.sin(),
+            y: self.y,
+            z: -self.x * angle.sin() + self.z * angle.cos(),
+        };
+
+        position + delta * (SPEED * self.time_delta as f64) as f32
+    }
+}
+
+/// Simulated time a client is allowed to move for, earned at the pace of the wall clock so
+/// inputs can't move a player faster than real time however they are sent.
+pub struct MovementBudget {
+    available: f64,
+    last_update: Instant,
+}
+
+impl MovementBudget {
+    pub fn new() -> Self {
+        Self {
+            available: 0.0,
+            last_update: Instant::now(),
+        }
+    }
+
+    /// earns the time elapsed since the last update
+    pub fn update(&mut self) {
+        let now = Instant::now();
+        self.earn(now - self.last_update);
+        self.last_update = now;
+    }
+
+    fn earn(&mut self, elapsed: Duration) {
+        self.available = (self.available + elapsed.as_secs_f64()).min(MAX_MOVEMENT_SLACK);
+    }
+
+    /// part of the input's frame that can be simulated
+    pub fn spend(&mut self, time_delta: f32) -> f32 {
+        let spent = (time_delta.max(0.0) as f64).min(self.available);
+        self.available -= spent;
+
+        spent as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn forward(time_delta: f32) -> PlayerInput {
+        PlayerInput {
+            z: 1.0,
+            time_delta,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn oversized_time_deltas_are_cut_short() {
+        let input = forward(6.0).quantized();
+        assert_eq!(input.time_delta, MAX_INPUT_TIME_DELTA);
+
+        let position = input.apply(WorldCoordinate {
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+        });
+        assert!(position.z <= (SPEED * MAX_INPUT_TIME_DELTA as f64) as f32 + 0.001);
+    }
+
+    #[test]
+    fn budget_follows_the_wall_clock() {
+        let mut budget = MovementBudget::new();
+        assert_eq!(budget.spend(0.016), 0.0);
+
+        budget.earn(Duration::from_millis(100));
+        assert!((budget.spend(0.06) - 0.06).abs() < 1e-6);
+        assert!((budget.spend(0.06) - 0.04).abs() < 1e-6);
+        assert_eq!(budget.spend(0.06), 0.0);
+
+        budget.earn(Duration::from_secs(60));
+        assert!((budget.spend(10.0) as f64 - MAX_MOVEMENT_SLACK).abs() < 1e-6);
+    }
+}
@@ -1,45 +1,501 @@
+use crate::network::block::{BlockAction, BlockCoordinate};
+use crate::network::channel::Channel;
+use crate::network::connection::{Backoff, Connection, ConnectionState};
+use crate::network::fragment::{Datagram, Fragmenter, Reassembler};
+use crate::network::movement::PlayerInput;
+use crate::network::packet::{Delivery, Packet};
+use crate::network::protocol::{
+    ClientMessage, GameMessage, PlayerId, ServerMessage, PROTOCOL_VERSION,
+};
+use crate::network::quantize::{QuantizedInput, QuantizedRotation};
+use crate::network::stats::{NetworkStats, StatsTracker};
+use crate::network::transport::{self, Transport};
 use crate::network::RemoteInfo;
 
-use core::events::{ClientEvent, ServerEvent};
+use core::chunk::ChunkGridCoordinate;
+use core::events::ClientEvent;
+use math::vector::Vector3;
 use std::io;
-use std::net::UdpSocket;
+use std::time::{Duration, Instant};
 
-const MAX_MESSAGE_SIZE: usize = 65535;
+/// an unchanged orientation is still sent this often, in case the last one was lost
+const LOOK_REFRESH_INTERVAL: Duration = Duration::from_secs(1);
 
 pub struct NetworkHandler {
-    socket: UdpSocket,
+    /// where to reconnect if the connection is lost, `None` if the transport was given as is
+    info: Option<RemoteInfo>,
+    transport: Box<dyn Transport>,
+    channel: Channel,
+    connection: Connection,
+    fragmenter: Fragmenter,
+    reassembler: Reassembler,
+    stats: StatsTracker,
+    last_look: Option<(QuantizedRotation, Instant)>,
+    /// only a connection that was established once is retried
+    was_connected: bool,
+    backoff: Option<Backoff>,
 }
 
 impl NetworkHandler {
     pub fn new(info: RemoteInfo) -> io::Result<Self> {
-        let socket = UdpSocket::bind("0.0.0.0:0")?;
-        socket.set_nonblocking(true).unwrap();
-        socket.connect(format!("{}:{}", info.ip, info.port))?;
+        let mut network = Self::with_transport(transport::connect(&info)?);
+        network.info = Some(info);
 
-        Ok(Self { socket })
+        Ok(network)
     }
 
-    pub fn send(&self, event: ClientEvent) {
-        let buffer = bincode::serialize(&event).unwrap();
+    pub fn with_transport(transport: Box<dyn Transport>) -> Self {
+        let mut network = Self {
+            info: None,
+            transport,
+            channel: Channel::new(),
+            connection: Connection::new(),
+            fragmenter: Fragmenter::new(),
+            reassembler: Reassembler::new(),
+            stats: StatsTracker::new(),
+            last_look: None,
+            was_connected: false,
+            backoff: None,
+        };
 
-        let _ = self.socket.send(&buffer);
+        network.send_handshake();
+        network
     }
 
-    pub fn process(&self) -> io::Result<Vec<ServerEvent>> {
-        let mut events = Vec::new();
+    pub fn state(&self) -> &ConnectionState {
+        self.connection.state()
+    }
+
+    pub fn stats(&self) -> NetworkStats {
+        self.stats.stats()
+    }
+
+    /// returns the new connection state once after every transition
+    pub fn take_state_change(&mut self) -> Option<ConnectionState> {
+        self.connection.take_state_change()
+    }
+
+    pub fn login(&mut self, username: String, token: Option<String>) {
+        if *self.connection.state() != ConnectionState::Connected {
+            return;
+        }
+
+        self.send_message(Delivery::Reliable, ClientMessage::Login { username, token });
+    }
+
+    pub fn send_chat(&mut self, text: String) {
+        if *self.connection.state() != ConnectionState::Connected {
+            return;
+        }
+
+        self.send_message(Delivery::Reliable, ClientMessage::Chat { text });
+    }
+
+    /// events are dropped unless the handshake completed
+    pub fn send(&mut self, event: ClientEvent) {
+        if *self.connection.state() != ConnectionState::Connected {
+            return;
+        }
+
+        let delivery = match event {
+            ClientEvent::PlayerMove { .. } => Delivery::Sequenced,
+            _ => Delivery::Reliable,
+        };
+
+        self.send_message(delivery, ClientMessage::Event(event));
+    }
+
+    /// inputs must have consecutive sequence numbers, as given by `Prediction`
+    pub fn send_inputs(&mut self, inputs: Vec<PlayerInput>) {
+        if *self.connection.state() != ConnectionState::Connected || inputs.is_empty() {
+            return;
+        }
+
+        self.send_message(
+            Delivery::Sequenced,
+            ClientMessage::PlayerInput {
+                first_sequence: inputs[0].sequence,
+                inputs: inputs.iter().map(QuantizedInput::new).collect(),
+            },
+        );
+    }
+
+    /// sends the camera euler angles if they changed since the last call
+    pub fn send_look(&mut self, euler_angles: Vector3) {
+        if *self.connection.state() != ConnectionState::Connected {
+            return;
+        }
+
+        let rotation = QuantizedRotation::new(euler_angles);
+
+        if let Some((last, sent)) = self.last_look {
+            if last == rotation && sent.elapsed() < LOOK_REFRESH_INTERVAL {
+                return;
+            }
+        }
+
+        self.last_look = Some((rotation, Instant::now()));
+        self.send_message(Delivery::Sequenced, ClientMessage::PlayerLook { rotation });
+    }
+
+    pub fn acknowledge_moves(&mut self, moves: Vec<(PlayerId, u16)>) {
+        if *self.connection.state() != ConnectionState::Connected {
+            return;
+        }
+
+        self.send_message(
+            Delivery::Sequenced,
+            ClientMessage::AcknowledgeMoves { moves },
+        );
+    }
+
+    pub fn request_chunks(&mut self, coords: Vec<ChunkGridCoordinate>) {
+        if *self.connection.state() != ConnectionState::Connected {
+            return;
+        }
+
+        self.send_message(Delivery::Reliable, ClientMessage::RequestChunks { coords });
+    }
+
+    pub fn edit_block(&mut self, edit: u32, coords: BlockCoordinate, action: BlockAction) {
+        if *self.connection.state() != ConnectionState::Connected {
+            return;
+        }
+
+        self.send_message(
+            Delivery::Reliable,
+            ClientMessage::EditBlock {
+                edit,
+                coords,
+                action,
+            },
+        );
+    }
+
+    /// sends a last disconnect event, the connection keeps being processed until the server
+    /// acknowledged it or the disconnect timed out
+    pub fn disconnect(&mut self) {
+        if *self.connection.state() == ConnectionState::Connected {
+            self.send(ClientEvent::PlayerDisconnect);
+            self.connection.set_state(ConnectionState::Disconnecting);
+        } else if self.connection.state().is_active() {
+            self.connection.set_state(ConnectionState::Disconnected);
+        }
+    }
+
+    pub fn process(&mut self) -> io::Result<Vec<GameMessage>> {
+        let mut messages = Vec::new();
+
+        if let ConnectionState::Reconnecting { .. } = self.connection.state() {
+            if self.backoff.as_ref().map_or(false, Backoff::is_due) {
+                self.reconnect();
+            }
+
+            return Ok(messages);
+        }
+
+        if !self.connection.state().is_active() {
+            return Ok(messages);
+        }
 
         loop {
-            let mut data = [0; MAX_MESSAGE_SIZE];
-            if self.socket.recv(&mut data).is_err() {
-                break;
+            let data = match self.transport.recv() {
+                Ok(Some(data)) => data,
+                Ok(None) => break,
+                Err(err) => {
+                    error!("<network> could not receive: {}", err);
+
+                    // the disconnect can't be acknowledged anymore
+                    if *self.connection.state() == ConnectionState::Disconnecting {
+                        self.connection.set_state(ConnectionState::Disconnected);
+                    } else {
+                        self.connection.set_state(ConnectionState::Failed {
+                            reason: err.to_string(),
+                        });
+                        self.schedule_reconnect();
+                    }
+
+                    return Ok(messages);
+                }
+            };
+
+            self.stats.received(data.len());
+
+            let datagram: Datagram = match bincode::deserialize(&data) {
+                Ok(datagram) => datagram,
+                Err(err) => {
+                    error!("<network> {}", err);
+                    self.stats.decode_failed();
+                    continue;
+                }
+            };
+
+            self.connection.received();
+
+            let buffer = match self.reassembler.insert(datagram) {
+                Some(buffer) => buffer,
+                None => continue,
+            };
+
+            let packet: Packet = match bincode::deserialize(&buffer) {
+                Ok(packet) => packet,
+                Err(err) => {
+                    error!("<network> {}", err);
+                    self.stats.decode_failed();
+                    continue;
+                }
+            };
+
+            for payload in self.channel.receive(packet) {
+                match bincode::deserialize(&payload) {
+                    Ok(message) => self.handle_message(message, &mut messages),
+                    Err(err) => {
+                        error!("<network> {}", err);
+                        self.stats.decode_failed();
+                    }
+                }
+            }
+        }
+
+        self.reassembler.expire();
+        self.connection.update();
+        self.stats.update();
+
+        let stats = self.stats.stats();
+        if let Some(rtt) = stats.rtt {
+            self.channel.set_round_trip(rtt, stats.jitter);
+        }
+
+        if *self.connection.state() == ConnectionState::TimedOut {
+            self.schedule_reconnect();
+            return Ok(messages);
+        }
+
+        if *self.connection.state() == ConnectionState::Connected {
+            if let Some(id) = self.stats.poll_ping() {
+                self.send_message(Delivery::Sequenced, ClientMessage::Ping { id });
             }
+        }
+
+        if self.connection.needs_heartbeat() {
+            self.send_message(Delivery::Sequenced, ClientMessage::Heartbeat);
+        }
+
+        for packet in self.channel.flush() {
+            self.send_packet(&packet);
+        }
+
+        if *self.connection.state() == ConnectionState::Disconnecting && !self.channel.has_pending()
+        {
+            self.connection.set_state(ConnectionState::Disconnected);
+        }
+
+        Ok(messages)
+    }
+
+    fn handle_message(&mut self, message: ServerMessage, messages: &mut Vec<GameMessage>) {
+        match message {
+            ServerMessage::HandshakeAccepted => {
+                self.connection.set_state(ConnectionState::Connected);
+                self.was_connected = true;
+                self.backoff = None;
+            }
+            ServerMessage::HandshakeRejected { protocol_version } => {
+                self.connection
+                    .set_state(ConnectionState::Rejected { protocol_version });
+            }
+            ServerMessage::Heartbeat => (),
+            ServerMessage::Pong { id } => self.stats.pong(id),
+            ServerMessage::Disconnect => {
+                self.connection.set_state(ConnectionState::Disconnected);
+            }
+            ServerMessage::Game(message) => {
+                if *self.connection.state() == ConnectionState::Connected {
+                    messages.push(message);
+                }
+            }
+        }
+    }
+
+    /// schedules the next reconnection attempt, the connection stays timed out once we give up
+    fn schedule_reconnect(&mut self) {
+        if self.info.is_none() || !self.was_connected {
+            return;
+        }
+
+        match self.backoff.get_or_insert_with(Backoff::new).next() {
+            Some(attempt) => self
+                .connection
+                .set_state(ConnectionState::Reconnecting { attempt }),
+            None => {
+                error!("<network> giving up on reconnecting to the server");
+                self.backoff = None;
+                self.connection.set_state(ConnectionState::TimedOut);
+            }
+        }
+    }
+
+    /// starts over with a new transport and a new handshake, the server sees a new client
+    fn reconnect(&mut self) {
+        let info = self.info.as_ref().unwrap();
+        info!("<network> reconnecting to {}:{}", info.ip, info.port);
+
+        match transport::connect(info) {
+            Ok(transport) => self.transport = transport,
+            Err(err) => {
+                warn!("<network> could not reconnect: {}", err);
+                self.schedule_reconnect();
+                return;
+            }
+        }
+
+        self.channel = Channel::new();
+        self.fragmenter = Fragmenter::new();
+        self.reassembler = Reassembler::new();
+        self.last_look = None;
+        self.stats = StatsTracker::new();
+        self.connection.restart();
+
+        self.send_handshake();
+    }
+
+    fn send_handshake(&mut self) {
+        self.send_message(
+            Delivery::Reliable,
+            ClientMessage::Handshake {
+                protocol_version: PROTOCOL_VERSION,
+            },
+        );
+    }
+
+    fn send_message(&mut self, delivery: Delivery, message: ClientMessage) {
+        let payload = bincode::serialize(&message).unwrap();
+        let packet = self.channel.send(delivery, payload);
+
+        self.send_packet(&packet);
+    }
 
-            match bincode::deserialize(&data) {
-                Ok(event) => events.push(event),
-                Err(err) => error!("{}", err),
+    fn send_packet(&mut self, packet: &Packet) {
+        let buffer = bincode::serialize(packet).unwrap();
+
+        for datagram in self.fragmenter.split(buffer) {
+            let buffer = bincode::serialize(&datagram).unwrap();
+
+            match self.transport.send(&buffer) {
+                Ok(size) => self.stats.sent(size),
+                Err(err) => warn!("<network> could not send packet: {}", err),
             }
         }
 
-        Ok(events)
+        self.connection.sent();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::transport::MemoryTransport;
+    use crate::network::MockServer;
+
+    use std::thread;
+
+    /// runs both ends until `done` returns true
+    fn run_until<F: Fn(&NetworkHandler) -> bool>(
+        network: &mut NetworkHandler,
+        server: &mut MockServer,
+        done: F,
+    ) {
+        let started = Instant::now();
+
+        while !done(network) {
+            assert!(
+                started.elapsed() < Duration::from_secs(5),
+                "still {}",
+                network.state()
+            );
+
+            server.process();
+            network.process().unwrap();
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    fn connect() -> (NetworkHandler, MockServer) {
+        let (mut server, info) = MockServer::memory(0);
+        let mut network = NetworkHandler::new(info).unwrap();
+
+        run_until(&mut network, &mut server, |network| {
+            *network.state() == ConnectionState::Connected
+        });
+
+        (network, server)
+    }
+
+    #[test]
+    fn a_broken_transport_fails_the_connection() {
+        let (client, server) = MemoryTransport::pair();
+        let mut server = MockServer::new(Box::new(server), 0);
+        let mut network = NetworkHandler::with_transport(Box::new(client));
+
+        run_until(&mut network, &mut server, |network| {
+            *network.state() == ConnectionState::Connected
+        });
+        drop(server);
+        network.process().unwrap();
+
+        assert!(!network.state().is_active());
+        assert!(matches!(network.state(), ConnectionState::Failed { .. }));
+    }
+
+    #[test]
+    fn a_timed_out_connection_is_reestablished() {
+        let (mut network, _silent) = connect();
+
+        // the server came back on another pipe
+        let (mut server, info) = MockServer::memory(0);
+        network.info = Some(info);
+
+        network.connection.expire();
+        network.process().unwrap();
+        assert_eq!(
+            network.take_state_change(),
+            Some(ConnectionState::Reconnecting { attempt: 1 })
+        );
+
+        run_until(&mut network, &mut server, |network| {
+            *network.state() == ConnectionState::Connected
+        });
+        assert!(network.backoff.is_none());
+    }
+
+    #[test]
+    fn disconnect_lasts_until_the_server_acknowledged_it() {
+        let (mut network, mut server) = connect();
+
+        network.disconnect();
+        network.process().unwrap();
+        assert_eq!(*network.state(), ConnectionState::Disconnecting);
+
+        run_until(&mut network, &mut server, |network| {
+            *network.state() == ConnectionState::Disconnected
+        });
+    }
+
+    #[test]
+    fn disconnect_gives_up_on_a_silent_server() {
+        let (mut network, _server) = connect();
+        let started = Instant::now();
+
+        network.disconnect();
+
+        while *network.state() == ConnectionState::Disconnecting {
+            assert!(started.elapsed() < Duration::from_secs(5));
+
+            network.process().unwrap();
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        assert_eq!(*network.state(), ConnectionState::Disconnected);
+        assert!(started.elapsed() >= Duration::from_secs(1));
     }
 }
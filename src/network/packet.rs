@@ -0,0 +1,27 @@
+use serde::{Deserialize, Serialize};
+
+pub type Sequence = u32;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Delivery {
+    /// delivered exactly once and in order, resent until acknowledged
+    Reliable,
+    /// may be lost, but is dropped if a newer message already arrived
+    Sequenced,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Message {
+    pub delivery: Delivery,
+    pub sequence: Sequence,
+    pub payload: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Packet {
+    /// next reliable sequence the sender is waiting for, everything before it was received
+    pub ack: Sequence,
+    /// bit `n` is set when reliable sequence `ack + 1 + n` was received out of order
+    pub ack_bits: u32,
+    pub message: Option<Message>,
+}
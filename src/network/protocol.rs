@@ -0,0 +1,152 @@
+use crate::network::block::{BlockAction, BlockCoordinate};
+use crate::network::quantize::{
+    EncodedPosition, QuantizedInput, QuantizedPosition, QuantizedRotation,
+};
+
+use core::block::Block;
+use core::chunk::{Chunk, ChunkGridCoordinate};
+use core::events::{ClientEvent, ServerEvent};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// bumped every time the wire format between the client and the server changes
+pub const PROTOCOL_VERSION: u32 = 14;
+pub const TICKS_PER_SECOND: f64 = 20.0;
+/// ticks in a whole day, a day lasts 20 minutes
+pub const DAY_LENGTH: u64 = 24000;
+
+pub type PlayerId = u128;
+
+#[derive(Serialize, Deserialize)]
+pub enum ClientMessage {
+    Handshake {
+        protocol_version: u32,
+    },
+    Heartbeat,
+    Ping {
+        id: u32,
+    },
+    /// sent once connected, `token` resumes the session the server gave the last time
+    Login {
+        username: String,
+        token: Option<String>,
+    },
+    Event(ClientEvent),
+    Chat {
+        text: String,
+    },
+    /// latest unacknowledged inputs numbered from `first_sequence`, the server skips the ones
+    /// it already processed
+    PlayerInput {
+        first_sequence: u32,
+        inputs: Vec<QuantizedInput>,
+    },
+    /// where the player looks, sent when it changes
+    PlayerLook {
+        rotation: QuantizedRotation,
+    },
+    /// latest `PlayerMoved` sequence received for each player, used as delta baselines
+    AcknowledgeMoves {
+        moves: Vec<(PlayerId, u16)>,
+    },
+    RequestChunks {
+        coords: Vec<ChunkGridCoordinate>,
+    },
+    /// `edit` identifies the edit in the server reply
+    EditBlock {
+        edit: u32,
+        coords: BlockCoordinate,
+        action: BlockAction,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+pub enum ServerMessage {
+    HandshakeAccepted,
+    HandshakeRejected { protocol_version: u32 },
+    Heartbeat,
+    Pong { id: u32 },
+    Disconnect,
+    Game(GameMessage),
+}
+
+/// why the server refused a login
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum LoginRejection {
+    InvalidUsername,
+    AlreadyPlaying,
+    /// the token does not resume the session of this username
+    InvalidToken,
+}
+
+impl fmt::Display for LoginRejection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoginRejection::InvalidUsername => write!(f, "this username is not allowed"),
+            LoginRejection::AlreadyPlaying => write!(f, "this username is already playing"),
+            LoginRejection::InvalidToken => write!(f, "invalid session token"),
+        }
+    }
+}
+
+/// messages forwarded to the game once the connection is established
+#[derive(Serialize, Deserialize)]
+pub enum GameMessage {
+    /// `token` resumes this session on the next login
+    LoginAccepted {
+        id: PlayerId,
+        token: String,
+    },
+    LoginRejected {
+        reason: LoginRejection,
+    },
+    Event(ServerEvent),
+    /// chat message broadcast to every player, `name` is `None` for notices from the server
+    Chat {
+        name: Option<String>,
+        text: String,
+    },
+    /// display name of a player, sent after it connected
+    PlayerName {
+        id: PlayerId,
+        name: String,
+    },
+    /// round trip time to the server of every player in milliseconds, sent regularly
+    PlayerLatencies {
+        latencies: Vec<(PlayerId, u16)>,
+    },
+    /// authoritative position of the main player after processing input `sequence`
+    PlayerState {
+        sequence: u32,
+        position: QuantizedPosition,
+    },
+    /// position and orientation of another player, `timestamp` is the server time in
+    /// milliseconds. Only sent while the player moves or the client did not acknowledge its
+    /// latest state.
+    PlayerMoved {
+        id: PlayerId,
+        sequence: u16,
+        timestamp: u64,
+        position: EncodedPosition,
+        rotation: QuantizedRotation,
+    },
+    ChunkData {
+        chunk: Chunk,
+    },
+    /// world clock, sent after the server info and then regularly so clients stay in sync.
+    /// `day_time` is the time of day in ticks, which a server can change on its own.
+    WorldTime {
+        tick: u64,
+        day_time: u32,
+    },
+    /// a block edit accepted by the server, sent to every player including its author
+    BlockChanged {
+        coords: BlockCoordinate,
+        block: Block,
+    },
+    /// answer to an `EditBlock`, sent after the matching `BlockChanged`
+    BlockEditResult {
+        edit: u32,
+        accepted: bool,
+    },
+}
@@ -0,0 +1,448 @@
+use crate::network::movement::{PlayerInput, MAX_INPUT_TIME_DELTA};
+
+use core::world::WorldCoordinate;
+use math::vector::Vector3;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// grid steps per block, positions are rounded to 1/256 of a block
+const POSITION_SCALE: f32 = 256.0;
+/// frame durations are rounded to a tenth of a millisecond
+const TIME_DELTA_SCALE: f32 = 10000.0;
+/// positions remembered on each side so deltas can reference them
+const MAX_HISTORY: usize = 64;
+
+/// angle in degrees to 1/65536 of a turn
+fn quantize_angle(angle: f32) -> u16 {
+    ((angle.rem_euclid(360.0) / 360.0 * 65536.0).round() as u32 % 65536) as u16
+}
+
+/// angle in 1/65536 of a turn to degrees, in [-180, 180)
+fn angle(angle: u16) -> f32 {
+    angle as i16 as f32 / 65536.0 * 360.0
+}
+
+/// indicates `a` was sent after `b`, accounting for wrapping
+fn is_newer(a: u16, b: u16) -> bool {
+    a != b && a.wrapping_sub(b) < 0x8000
+}
+
+/// position rounded to the fixed-point grid, what the server and the client agree on
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuantizedPosition {
+    x: i32,
+    y: i32,
+    z: i32,
+}
+
+impl QuantizedPosition {
+    pub fn new(position: WorldCoordinate) -> Self {
+        Self {
+            x: (position.x * POSITION_SCALE).round() as i32,
+            y: (position.y * POSITION_SCALE).round() as i32,
+            z: (position.z * POSITION_SCALE).round() as i32,
+        }
+    }
+
+    pub fn position(&self) -> WorldCoordinate {
+        WorldCoordinate {
+            x: self.x as f32 / POSITION_SCALE,
+            y: self.y as f32 / POSITION_SCALE,
+            z: self.z as f32 / POSITION_SCALE,
+        }
+    }
+
+    /// grid steps from `self` to `other`, `None` if they are too far apart to fit a delta
+    fn delta(&self, other: &Self) -> Option<[i16; 3]> {
+        let delta = |from: i32, to: i32| {
+            let delta = to as i64 - from as i64;
+            if delta >= i16::MIN as i64 && delta <= i16::MAX as i64 {
+                Some(delta as i16)
+            } else {
+                None
+            }
+        };
+
+        Some([
+            delta(self.x, other.x)?,
+            delta(self.y, other.y)?,
+            delta(self.z, other.z)?,
+        ])
+    }
+
+    fn offset(&self, delta: [i16; 3]) -> Self {
+        Self {
+            x: self.x.wrapping_add(delta[0] as i32),
+            y: self.y.wrapping_add(delta[1] as i32),
+            z: self.z.wrapping_add(delta[2] as i32),
+        }
+    }
+}
+
+/// where a player looks, rounded to 1/65536 of a turn
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuantizedRotation {
+    yaw: u16,
+    pitch: u16,
+}
+
+impl QuantizedRotation {
+    /// from camera euler angles in degrees, pitch around x and yaw around y
+    pub fn new(euler_angles: Vector3) -> Self {
+        Self {
+            yaw: quantize_angle(euler_angles.y),
+            pitch: quantize_angle(euler_angles.x),
+        }
+    }
+
+    pub fn euler_angles(&self) -> Vector3 {
+        Vector3 {
+            x: angle(self.pitch),
+            y: angle(self.yaw),
+            z: 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub enum EncodedPosition {
+    Absolute(QuantizedPosition),
+    /// grid steps from the position sent as `baseline`, which the client acknowledged
+    Delta {
+        baseline: u16,
+        delta: [i16; 3],
+    },
+}
+
+/// Server side encoding of the positions of one player. Positions are sent relative to the
+/// last one the client acknowledged, and not sent at all once the client has the current one
+/// and the player still looks the same way.
+pub struct PositionEncoder {
+    next_sequence: u16,
+    sent: VecDeque<(u16, QuantizedPosition, QuantizedRotation)>,
+    acknowledged: Option<(u16, QuantizedPosition, QuantizedRotation)>,
+}
+
+impl PositionEncoder {
+    pub fn new() -> Self {
+        Self {
+            next_sequence: 0,
+            sent: VecDeque::new(),
+            acknowledged: None,
+        }
+    }
+
+    /// sequence and encoding of the state to send, `None` if the client is up to date
+    pub fn encode(
+        &mut self,
+        position: WorldCoordinate,
+        euler_angles: Vector3,
+    ) -> Option<(u16, EncodedPosition, QuantizedRotation)> {
+        let position = QuantizedPosition::new(position);
+        let rotation = QuantizedRotation::new(euler_angles);
+
+        let encoded = match self.acknowledged {
+            Some((_, acknowledged, acknowledged_rotation))
+                if acknowledged == position && acknowledged_rotation == rotation =>
+            {
+                return None
+            }
+            // the client only keeps so many positions, older baselines might be gone
+            Some((baseline, acknowledged, _))
+                if (self.next_sequence.wrapping_sub(baseline) as usize) < MAX_HISTORY / 2 =>
+            {
+                match acknowledged.delta(&position) {
+                    Some(delta) => EncodedPosition::Delta { baseline, delta },
+                    None => EncodedPosition::Absolute(position),
+                }
+            }
+            _ => EncodedPosition::Absolute(position),
+        };
+
+        let sequence = self.next_sequence;
+        self.next_sequence = self.next_sequence.wrapping_add(1);
+
+        if self.sent.len() >= MAX_HISTORY {
+            self.sent.pop_front();
+        }
+        self.sent.push_back((sequence, position, rotation));
+
+        Some((sequence, encoded, rotation))
+    }
+
+    pub fn acknowledge(&mut self, sequence: u16) {
+        if let Some((acknowledged, _, _)) = self.acknowledged {
+            if !is_newer(sequence, acknowledged) {
+                return;
+            }
+        }
+
+        if let Some(entry) = self.sent.iter().find(|(sent, _, _)| *sent == sequence) {
+            self.acknowledged = Some(*entry);
+            self.sent.retain(|(sent, _, _)| is_newer(*sent, sequence));
+        }
+    }
+}
+
+/// Client side decoding of the positions of one player, the counterpart of `PositionEncoder`.
+pub struct PositionDecoder {
+    received: VecDeque<(u16, QuantizedPosition)>,
+    latest: Option<u16>,
+    acknowledged: Option<u16>,
+}
+
+impl PositionDecoder {
+    pub fn new() -> Self {
+        Self {
+            received: VecDeque::new(),
+            latest: None,
+            acknowledged: None,
+        }
+    }
+
+    /// `None` if the baseline of a delta is unknown, the server falls back to absolute
+    /// positions once it stops getting acknowledgements
+    pub fn decode(&mut self, sequence: u16, position: EncodedPosition) -> Option<WorldCoordinate> {
+        let position = match position {
+            EncodedPosition::Absolute(position) => position,
+            EncodedPosition::Delta { baseline, delta } => self
+                .received
+                .iter()
+                .find(|(received, _)| *received == baseline)?
+                .1
+                .offset(delta),
+        };
+
+        if self
+            .latest
+            .map_or(true, |latest| is_newer(sequence, latest))
+        {
+            self.latest = Some(sequence);
+        }
+
+        if self.received.len() >= MAX_HISTORY {
+            self.received.pop_front();
+        }
+        self.received.push_back((sequence, position));
+
+        Some(position.position())
+    }
+
+    /// latest sequence received, once, so it can be acknowledged to the server
+    pub fn take_acknowledgement(&mut self) -> Option<u16> {
+        if self.latest == self.acknowledged {
+            return None;
+        }
+
+        self.acknowledged = self.latest;
+        self.latest
+    }
+}
+
+/// per-frame input as sent on the wire
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct QuantizedInput {
+    /// two bits per axis, x in the lowest ones, `0` moving backward and `2` forward
+    axes: u8,
+    /// yaw in 1/65536 of a turn
+    yaw: u16,
+    /// frame duration in tenths of milliseconds
+    time_delta: u16,
+}
+
+impl QuantizedInput {
+    pub fn new(input: &PlayerInput) -> Self {
+        let axis = |value: f32| (value.round().max(-1.0).min(1.0) as i8 + 1) as u8;
+        let time_delta = (input.time_delta * TIME_DELTA_SCALE)
+            .round()
+            .min(u16::MAX as f32);
+
+        Self {
+            axes: axis(input.x) | axis(input.y) << 2 | axis(input.z) << 4,
+            yaw: quantize_angle(input.yaw),
+            time_delta: time_delta as u16,
+        }
+    }
+
+    /// the axes and the frame duration are clamped, they can't be trusted coming from a client
+    pub fn input(&self, sequence: u32) -> PlayerInput {
+        let axis = |shift: u8| (((self.axes >> shift) & 0b11) as f32 - 1.0).min(1.0);
+
+        PlayerInput {
+            sequence,
+            x: axis(0),
+            y: axis(2),
+            z: axis(4),
+            yaw: angle(self.yaw),
+            time_delta: (self.time_delta as f32 / TIME_DELTA_SCALE).min(MAX_INPUT_TIME_DELTA),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(x: f32) -> WorldCoordinate {
+        WorldCoordinate { x, y: 64.0, z: 0.0 }
+    }
+
+    fn looking() -> Vector3 {
+        Vector3 {
+            x: 0.0,
+            y: 90.0,
+            z: 0.0,
+        }
+    }
+
+    /// decodes what the encoder sends, acknowledging it like the client does
+    fn send(
+        encoder: &mut PositionEncoder,
+        decoder: &mut PositionDecoder,
+        x: f32,
+    ) -> (u16, EncodedPosition) {
+        let (sequence, encoded, _) = encoder.encode(at(x), looking()).unwrap();
+        assert_eq!(decoder.decode(sequence, encoded).unwrap().x, x);
+
+        encoder.acknowledge(decoder.take_acknowledgement().unwrap());
+        (sequence, encoded)
+    }
+
+    #[test]
+    fn sequences_wrap_around() {
+        assert!(is_newer(1, 0));
+        assert!(!is_newer(0, 1));
+        assert!(!is_newer(5, 5));
+        assert!(is_newer(0, u16::MAX));
+        assert!(is_newer(10, u16::MAX - 10));
+        assert!(!is_newer(u16::MAX, 0));
+    }
+
+    #[test]
+    fn positions_are_sent_relative_to_the_acknowledged_one() {
+        let mut encoder = PositionEncoder::new();
+        let mut decoder = PositionDecoder::new();
+
+        let (_, first) = send(&mut encoder, &mut decoder, 1.0);
+        assert!(matches!(first, EncodedPosition::Absolute(_)));
+
+        let (_, second) = send(&mut encoder, &mut decoder, 2.5);
+        match second {
+            EncodedPosition::Delta { baseline, delta } => {
+                assert_eq!(baseline, 0);
+                assert_eq!(delta, [384, 0, 0]);
+            }
+            EncodedPosition::Absolute(_) => panic!("expected a delta"),
+        }
+
+        // nothing to send once the client has the current state
+        assert!(encoder.encode(at(2.5), looking()).is_none());
+    }
+
+    #[test]
+    fn old_baselines_fall_back_to_absolute_positions() {
+        let mut encoder = PositionEncoder::new();
+        let mut decoder = PositionDecoder::new();
+        send(&mut encoder, &mut decoder, 0.0);
+
+        // never acknowledged, so the client might have forgotten the baseline by now
+        for n in 1..MAX_HISTORY / 2 {
+            let (_, encoded, _) = encoder.encode(at(n as f32), looking()).unwrap();
+            assert!(matches!(encoded, EncodedPosition::Delta { .. }));
+        }
+
+        let (_, encoded, _) = encoder.encode(at(100.0), looking()).unwrap();
+        assert!(matches!(encoded, EncodedPosition::Absolute(_)));
+    }
+
+    #[test]
+    fn far_positions_are_sent_absolute() {
+        let mut encoder = PositionEncoder::new();
+        let mut decoder = PositionDecoder::new();
+        send(&mut encoder, &mut decoder, 0.0);
+
+        let (_, encoded) = send(&mut encoder, &mut decoder, 1000.0);
+        assert!(matches!(encoded, EncodedPosition::Absolute(_)));
+    }
+
+    #[test]
+    fn deltas_on_unknown_or_evicted_baselines_are_dropped() {
+        let mut decoder = PositionDecoder::new();
+        let delta = EncodedPosition::Delta {
+            baseline: 0,
+            delta: [1, 0, 0],
+        };
+        assert!(decoder.decode(1, delta).is_none());
+
+        for sequence in 0..=MAX_HISTORY as u16 {
+            let absolute = EncodedPosition::Absolute(QuantizedPosition::new(at(0.0)));
+            decoder.decode(sequence, absolute).unwrap();
+        }
+
+        assert!(decoder.decode(100, delta).is_none());
+    }
+
+    #[test]
+    fn encoding_carries_on_across_the_wrap() {
+        let mut encoder = PositionEncoder::new();
+        let mut decoder = PositionDecoder::new();
+        encoder.next_sequence = u16::MAX - 1;
+
+        send(&mut encoder, &mut decoder, 0.0);
+        send(&mut encoder, &mut decoder, 1.0);
+        let (sequence, encoded) = send(&mut encoder, &mut decoder, 2.0);
+
+        assert_eq!(sequence, 0);
+        match encoded {
+            EncodedPosition::Delta { baseline, .. } => assert_eq!(baseline, u16::MAX),
+            EncodedPosition::Absolute(_) => panic!("expected a delta"),
+        }
+
+        // an acknowledgement from before the wrap does not move the baseline back
+        encoder.acknowledge(u16::MAX);
+        assert_eq!(encoder.acknowledged.unwrap().0, 0);
+    }
+
+    #[test]
+    fn inputs_round_trip_the_same_on_both_sides() {
+        let input = PlayerInput {
+            sequence: 0,
+            x: -1.0,
+            y: 0.0,
+            z: 1.0,
+            yaw: 123.456,
+            time_delta: 0.016_67,
+        };
+
+        // what the client predicts with is exactly what the server decodes
+        let predicted = input.quantized();
+        let decoded = QuantizedInput::new(&input).input(7);
+        let again = predicted.quantized();
+
+        for other in [decoded, again].iter() {
+            assert_eq!(other.x, predicted.x);
+            assert_eq!(other.y, predicted.y);
+            assert_eq!(other.z, predicted.z);
+            assert_eq!(other.yaw, predicted.yaw);
+            assert_eq!(other.time_delta, predicted.time_delta);
+        }
+
+        assert_eq!(decoded.sequence, 7);
+        assert_eq!((predicted.x, predicted.y, predicted.z), (-1.0, 0.0, 1.0));
+        assert!((predicted.yaw - 123.456).abs() < 0.01);
+        assert!((predicted.time_delta - 0.016_67).abs() < 0.000_1);
+    }
+
+    #[test]
+    fn forged_axes_are_clamped() {
+        let input = QuantizedInput {
+            axes: 0b11_1111,
+            yaw: 0,
+            time_delta: u16::MAX,
+        }
+        .input(0);
+
+        assert_eq!((input.x, input.y, input.z), (1.0, 1.0, 1.0));
+        assert_eq!(input.time_delta, MAX_INPUT_TIME_DELTA);
+    }
+}
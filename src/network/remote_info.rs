@@ -1,14 +1,45 @@
+use crate::network::discovery::ServerDetails;
+use crate::network::transport::{MemoryTransport, NetworkConditions, TransportType};
+
 // TODO: remove this dead_code attribute when we have a menu for this
 #[allow(dead_code)]
 #[derive(Debug, Clone, PartialEq)]
 pub struct RemoteInfo {
     pub ip: String,
     pub port: u16,
+    pub transport: TransportType,
+    /// simulated latency, loss and so on, `None` to use the network as it is
+    pub conditions: Option<NetworkConditions>,
+    /// name, player count and so on, only known for servers found on the local network
+    pub details: Option<ServerDetails>,
 }
 
 impl RemoteInfo {
     #[allow(dead_code)]
     pub fn new(ip: String, port: u16) -> Self {
-        Self { ip, port }
+        Self {
+            ip,
+            port,
+            transport: TransportType::Udp,
+            conditions: None,
+            details: None,
+        }
+    }
+
+    /// server running in the same process, reached through one end of a memory pipe
+    pub fn memory(transport: MemoryTransport) -> Self {
+        Self {
+            ip: String::from("memory"),
+            port: 0,
+            transport: TransportType::Memory(transport),
+            conditions: None,
+            details: None,
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn with_transport(mut self, transport: TransportType) -> Self {
+        self.transport = transport;
+        self
     }
 }
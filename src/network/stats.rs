@@ -0,0 +1,270 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+const PING_INTERVAL: Duration = Duration::from_millis(500);
+const PING_TIMEOUT: Duration = Duration::from_secs(2);
+/// number of pings the packet loss is computed on
+const LOSS_WINDOW: usize = 20;
+const RATE_WINDOW: Duration = Duration::from_secs(1);
+const RTT_SMOOTHING: f64 = 1.0 / 8.0;
+const JITTER_SMOOTHING: f64 = 1.0 / 16.0;
+
+#[derive(Debug, Copy, Clone, Default)]
+pub struct NetworkStats {
+    /// smoothed round trip time in milliseconds, `None` until the first pong
+    pub rtt: Option<f64>,
+    /// mean deviation between consecutive round trip times in milliseconds
+    pub jitter: f64,
+    /// ratio of pings that never got an answer, between 0 and 1
+    pub packet_loss: f64,
+    pub bytes_sent_per_second: f64,
+    pub bytes_received_per_second: f64,
+    pub packets_sent_per_second: f64,
+    pub packets_received_per_second: f64,
+    /// total of datagrams and messages that could not be deserialized
+    pub decode_failures: u64,
+}
+
+impl fmt::Display for NetworkStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.rtt {
+            Some(rtt) => write!(f, "rtt: {:.1}ms, ", rtt)?,
+            None => write!(f, "rtt: -, ")?,
+        }
+
+        write!(
+            f,
+            "jitter: {:.1}ms, loss: {:.1}%, up: {:.0}B/s ({:.0}p/s), down: {:.0}B/s ({:.0}p/s), decode failures: {}",
+            self.jitter,
+            self.packet_loss * 100.0,
+            self.bytes_sent_per_second,
+            self.packets_sent_per_second,
+            self.bytes_received_per_second,
+            self.packets_received_per_second,
+            self.decode_failures
+        )
+    }
+}
+
+#[derive(Debug, Default)]
+struct Counter {
+    bytes: usize,
+    packets: usize,
+}
+
+pub struct StatsTracker {
+    stats: NetworkStats,
+
+    next_ping: u32,
+    pings: HashMap<u32, Instant>,
+    last_ping: Instant,
+    last_rtt: Option<f64>,
+    ping_results: VecDeque<bool>,
+
+    sent: Counter,
+    received: Counter,
+    window_start: Instant,
+}
+
+impl StatsTracker {
+    pub fn new() -> Self {
+        Self {
+            stats: NetworkStats::default(),
+
+            next_ping: 0,
+            pings: HashMap::new(),
+            last_ping: Instant::now(),
+            last_rtt: None,
+            ping_results: VecDeque::new(),
+
+            sent: Counter::default(),
+            received: Counter::default(),
+            window_start: Instant::now(),
+        }
+    }
+
+    pub fn stats(&self) -> NetworkStats {
+        self.stats
+    }
+
+    pub fn sent(&mut self, bytes: usize) {
+        self.sent.bytes += bytes;
+        self.sent.packets += 1;
+    }
+
+    pub fn received(&mut self, bytes: usize) {
+        self.received.bytes += bytes;
+        self.received.packets += 1;
+    }
+
+    pub fn decode_failed(&mut self) {
+        self.stats.decode_failures += 1;
+    }
+
+    /// returns the id of a new ping when it's time to send one
+    pub fn poll_ping(&mut self) -> Option<u32> {
+        if self.last_ping.elapsed() < PING_INTERVAL {
+            return None;
+        }
+
+        let id = self.next_ping;
+        self.next_ping = self.next_ping.wrapping_add(1);
+        self.last_ping = Instant::now();
+        self.pings.insert(id, Instant::now());
+
+        Some(id)
+    }
+
+    pub fn pong(&mut self, id: u32) {
+        let sent = match self.pings.remove(&id) {
+            Some(sent) => sent,
+            None => return,
+        };
+
+        let sample = sent.elapsed().as_secs_f64() * 1000.0;
+
+        self.stats.rtt = Some(match self.stats.rtt {
+            Some(rtt) => rtt + (sample - rtt) * RTT_SMOOTHING,
+            None => sample,
+        });
+
+        if let Some(last) = self.last_rtt {
+            self.stats.jitter += ((sample - last).abs() - self.stats.jitter) * JITTER_SMOOTHING;
+        }
+
+        self.last_rtt = Some(sample);
+        self.push_ping_result(true);
+    }
+
+    /// to call every frame, expires lost pings and refreshes the rates
+    pub fn update(&mut self) {
+        let pending = self.pings.len();
+        self.pings.retain(|_, sent| sent.elapsed() < PING_TIMEOUT);
+
+        for _ in self.pings.len()..pending {
+            self.push_ping_result(false);
+        }
+
+        let elapsed = self.window_start.elapsed();
+        if elapsed >= RATE_WINDOW {
+            let seconds = elapsed.as_secs_f64();
+
+            self.stats.bytes_sent_per_second = self.sent.bytes as f64 / seconds;
+            self.stats.packets_sent_per_second = self.sent.packets as f64 / seconds;
+            self.stats.bytes_received_per_second = self.received.bytes as f64 / seconds;
+            self.stats.packets_received_per_second = self.received.packets as f64 / seconds;
+
+            self.sent = Counter::default();
+            self.received = Counter::default();
+            self.window_start = Instant::now();
+        }
+    }
+
+    fn push_ping_result(&mut self, answered: bool) {
+        if self.ping_results.len() >= LOSS_WINDOW {
+            self.ping_results.pop_front();
+        }
+
+        self.ping_results.push_back(answered);
+
+        let lost = self
+            .ping_results
+            .iter()
+            .filter(|answered| !**answered)
+            .count();
+        self.stats.packet_loss = lost as f64 / self.ping_results.len() as f64;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// answers a ping as if it was sent `rtt` milliseconds ago
+    fn answer(tracker: &mut StatsTracker, rtt: u64) {
+        let id = tracker.next_ping;
+        tracker.next_ping += 1;
+        tracker
+            .pings
+            .insert(id, Instant::now() - Duration::from_millis(rtt));
+        tracker.pong(id);
+    }
+
+    fn lose(tracker: &mut StatsTracker) {
+        let id = tracker.next_ping;
+        tracker.next_ping += 1;
+        tracker.pings.insert(id, Instant::now() - PING_TIMEOUT);
+        tracker.update();
+    }
+
+    #[test]
+    fn round_trip_and_jitter_are_smoothed() {
+        let mut tracker = StatsTracker::new();
+        assert_eq!(tracker.stats().rtt, None);
+
+        answer(&mut tracker, 100);
+        let first = tracker.stats().rtt.unwrap();
+        assert!((first - 100.0).abs() < 5.0);
+        assert_eq!(tracker.stats().jitter, 0.0);
+
+        answer(&mut tracker, 260);
+        let stats = tracker.stats();
+        assert!((stats.rtt.unwrap() - (first + 20.0)).abs() < 5.0);
+        assert!((stats.jitter - 10.0).abs() < 1.0);
+    }
+
+    #[test]
+    fn unknown_pongs_are_ignored() {
+        let mut tracker = StatsTracker::new();
+        tracker.pong(42);
+
+        assert_eq!(tracker.stats().rtt, None);
+        assert_eq!(tracker.stats().packet_loss, 0.0);
+    }
+
+    #[test]
+    fn loss_is_computed_over_the_last_pings() {
+        let mut tracker = StatsTracker::new();
+        for _ in 0..LOSS_WINDOW / 2 {
+            answer(&mut tracker, 10);
+            lose(&mut tracker);
+        }
+        assert_eq!(tracker.stats().packet_loss, 0.5);
+
+        // older results leave the window
+        for _ in 0..LOSS_WINDOW / 2 {
+            answer(&mut tracker, 10);
+        }
+        assert_eq!(tracker.stats().packet_loss, 0.25);
+
+        for _ in 0..LOSS_WINDOW {
+            answer(&mut tracker, 10);
+        }
+        assert_eq!(tracker.stats().packet_loss, 0.0);
+    }
+
+    #[test]
+    fn rates_are_refreshed_once_per_window() {
+        let mut tracker = StatsTracker::new();
+        tracker.sent(100);
+        tracker.sent(300);
+        tracker.received(50);
+
+        tracker.update();
+        assert_eq!(tracker.stats().bytes_sent_per_second, 0.0);
+
+        tracker.window_start = Instant::now() - RATE_WINDOW * 2;
+        tracker.update();
+
+        let stats = tracker.stats();
+        assert!((stats.bytes_sent_per_second - 200.0).abs() < 1.0);
+        assert!((stats.packets_sent_per_second - 1.0).abs() < 0.01);
+        assert!((stats.bytes_received_per_second - 25.0).abs() < 1.0);
+        assert!((stats.packets_received_per_second - 0.5).abs() < 0.01);
+
+        // counters start over with the new window
+        assert_eq!(tracker.sent.bytes, 0);
+        assert_eq!(tracker.received.packets, 0);
+    }
+}
@@ -0,0 +1,103 @@
+use crate::network::transport::Transport;
+
+use std::fmt;
+use std::io::{self, ErrorKind};
+use std::sync::mpsc::{channel, Receiver, Sender, TryRecvError};
+use std::sync::{Arc, Mutex};
+
+/// One end of an in-process pipe, deterministic and free of any real socket.
+#[derive(Clone)]
+pub struct MemoryTransport {
+    sender: Sender<Vec<u8>>,
+    receiver: Arc<Mutex<Receiver<Vec<u8>>>>,
+}
+
+impl MemoryTransport {
+    /// two connected ends, what one sends the other receives
+    pub fn pair() -> (Self, Self) {
+        let (a_sender, a_receiver) = channel();
+        let (b_sender, b_receiver) = channel();
+
+        (
+            Self {
+                sender: a_sender,
+                receiver: Arc::new(Mutex::new(b_receiver)),
+            },
+            Self {
+                sender: b_sender,
+                receiver: Arc::new(Mutex::new(a_receiver)),
+            },
+        )
+    }
+}
+
+impl Transport for MemoryTransport {
+    fn send(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.sender
+            .send(data.to_vec())
+            .map_err(|_| io::Error::from(ErrorKind::BrokenPipe))?;
+
+        Ok(data.len())
+    }
+
+    fn recv(&mut self) -> io::Result<Option<Vec<u8>>> {
+        match self.receiver.lock().unwrap().try_recv() {
+            Ok(data) => Ok(Some(data)),
+            Err(TryRecvError::Empty) => Ok(None),
+            Err(TryRecvError::Disconnected) => Err(ErrorKind::BrokenPipe.into()),
+        }
+    }
+}
+
+impl fmt::Debug for MemoryTransport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "MemoryTransport")
+    }
+}
+
+impl PartialEq for MemoryTransport {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.receiver, &other.receiver)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn datagrams_arrive_whole_and_in_order() {
+        let (mut client, mut server) = MemoryTransport::pair();
+
+        assert_eq!(client.send(&[1, 2, 3]).unwrap(), 3);
+        assert_eq!(client.send(&[4]).unwrap(), 1);
+        server.send(&[5, 6]).unwrap();
+
+        assert_eq!(server.recv().unwrap(), Some(vec![1, 2, 3]));
+        assert_eq!(server.recv().unwrap(), Some(vec![4]));
+        assert_eq!(server.recv().unwrap(), None);
+        assert_eq!(client.recv().unwrap(), Some(vec![5, 6]));
+        assert_eq!(client.recv().unwrap(), None);
+    }
+
+    #[test]
+    fn clones_share_the_same_end() {
+        let (client, mut server) = MemoryTransport::pair();
+        let mut clone = client.clone();
+
+        assert_eq!(clone, client);
+        assert_ne!(clone, server);
+
+        clone.send(&[1]).unwrap();
+        assert_eq!(server.recv().unwrap(), Some(vec![1]));
+    }
+
+    #[test]
+    fn a_closed_end_is_a_broken_pipe() {
+        let (mut client, server) = MemoryTransport::pair();
+        drop(server);
+
+        assert_eq!(client.recv().unwrap_err().kind(), ErrorKind::BrokenPipe);
+        assert_eq!(client.send(&[1]).unwrap_err().kind(), ErrorKind::BrokenPipe);
+    }
+}
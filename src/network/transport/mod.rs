@@ -0,0 +1,50 @@
+mod memory;
+mod simulator;
+mod tcp;
+mod udp;
+
+pub use self::memory::MemoryTransport;
+pub use self::simulator::{NetworkConditions, SimulatedTransport};
+pub use self::tcp::TcpTransport;
+pub use self::udp::UdpTransport;
+
+use crate::network::RemoteInfo;
+
+use std::io;
+
+/// Moves whole datagrams between the client and the server. Implementations never block.
+pub trait Transport: Send {
+    fn send(&mut self, data: &[u8]) -> io::Result<usize>;
+
+    /// next datagram waiting to be read, `None` if there is none
+    fn recv(&mut self) -> io::Result<Option<Vec<u8>>>;
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TransportType {
+    Udp,
+    /// for networks blocking UDP, datagrams are sent with a length prefix
+    // TODO: remove this dead_code attribute when we have a menu for this
+    #[allow(dead_code)]
+    Tcp,
+    /// both ends live in the same process, mostly for tests and offline games
+    Memory(MemoryTransport),
+}
+
+pub fn connect(info: &RemoteInfo) -> io::Result<Box<dyn Transport>> {
+    let address = format!("{}:{}", info.ip, info.port);
+
+    let transport: Box<dyn Transport> = match &info.transport {
+        TransportType::Udp => Box::new(UdpTransport::connect(address)?),
+        TransportType::Tcp => Box::new(TcpTransport::connect(address)?),
+        TransportType::Memory(transport) => Box::new(transport.clone()),
+    };
+
+    Ok(match info.conditions {
+        Some(conditions) => {
+            info!("<network> simulating network conditions: {}", conditions);
+            Box::new(SimulatedTransport::new(transport, conditions))
+        }
+        None => transport,
+    })
+}
@@ -0,0 +1,294 @@
+use crate::network::transport::Transport;
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// minimum extra delay given to a datagram picked for reordering
+const REORDER_DELAY: u64 = 50;
+
+/// Bad network to reproduce locally, applied to each direction independently.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetworkConditions {
+    /// one way delay in milliseconds
+    pub latency: u64,
+    /// random delay in milliseconds added on top of the latency
+    pub jitter: u64,
+    /// chance for a datagram to be dropped, from 0 to 1
+    pub loss: f64,
+    /// chance for a datagram to be delivered twice, from 0 to 1
+    pub duplication: f64,
+    /// chance for a datagram to be held back until after the next ones, from 0 to 1
+    pub reordering: f64,
+}
+
+impl NetworkConditions {
+    /// reads the conditions from a JSON file, missing fields are left to a perfect network
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let data = fs::read_to_string(path)?;
+        serde_json::from_str(&data).map_err(|err| io::Error::new(ErrorKind::InvalidData, err))
+    }
+
+    /// checks the probabilities are between 0 and 1
+    pub fn validate(&self) -> io::Result<()> {
+        let probabilities = [
+            ("loss", self.loss),
+            ("duplication", self.duplication),
+            ("reordering", self.reordering),
+        ];
+
+        for (name, probability) in probabilities.iter() {
+            if !(0.0..=1.0).contains(probability) {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("{} must be between 0 and 1, got {}", name, probability),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for NetworkConditions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "latency {}ms ±{}ms, loss {:.1}%, duplication {:.1}%, reordering {:.1}%",
+            self.latency,
+            self.jitter,
+            self.loss * 100.0,
+            self.duplication * 100.0,
+            self.reordering * 100.0
+        )
+    }
+}
+
+/// xorshift generator, good enough to roll the dice for the simulation
+struct Random(u64);
+
+impl Random {
+    fn new() -> Self {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_nanos() as u64);
+
+        Self(seed | 1)
+    }
+
+    /// uniform value in [0, 1)
+    fn next(&mut self) -> f64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+
+        (self.0 >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn chance(&mut self, probability: f64) -> bool {
+        self.next() < probability
+    }
+}
+
+struct Delayed {
+    due: Instant,
+    data: Vec<u8>,
+}
+
+/// Wraps another transport and holds, drops, duplicates and reorders what goes through it.
+/// Datagrams are only moved when the transport is used, so it must be polled every frame.
+pub struct SimulatedTransport {
+    inner: Box<dyn Transport>,
+    conditions: NetworkConditions,
+    random: Random,
+    /// both queues are sorted by due time
+    outgoing: Vec<Delayed>,
+    incoming: Vec<Delayed>,
+}
+
+impl SimulatedTransport {
+    pub fn new(inner: Box<dyn Transport>, conditions: NetworkConditions) -> Self {
+        Self {
+            inner,
+            conditions,
+            random: Random::new(),
+            outgoing: Vec::new(),
+            incoming: Vec::new(),
+        }
+    }
+
+    fn schedule(
+        conditions: &NetworkConditions,
+        random: &mut Random,
+        queue: &mut Vec<Delayed>,
+        data: &[u8],
+    ) {
+        if random.chance(conditions.loss) {
+            return;
+        }
+
+        let copies = if random.chance(conditions.duplication) {
+            2
+        } else {
+            1
+        };
+
+        for _ in 0..copies {
+            let mut delay = conditions.latency as f64 + random.next() * conditions.jitter as f64;
+
+            if random.chance(conditions.reordering) {
+                delay += conditions.jitter.max(REORDER_DELAY) as f64;
+            }
+
+            let due = Instant::now() + Duration::from_secs_f64(delay / 1000.0);
+            let index = queue
+                .iter()
+                .position(|delayed| delayed.due > due)
+                .unwrap_or(queue.len());
+
+            queue.insert(
+                index,
+                Delayed {
+                    due,
+                    data: data.to_vec(),
+                },
+            );
+        }
+    }
+
+    /// sends the datagrams that are due, one that could not be sent is kept for the next flush
+    /// since only the simulation is allowed to lose datagrams. Errors are only logged, the
+    /// datagrams were already accepted.
+    fn flush(&mut self) {
+        let now = Instant::now();
+
+        while self
+            .outgoing
+            .first()
+            .map_or(false, |delayed| delayed.due <= now)
+        {
+            let delayed = self.outgoing.remove(0);
+
+            if let Err(err) = self.inner.send(&delayed.data) {
+                warn!("<network> could not send a delayed datagram: {}", err);
+                self.outgoing.insert(0, delayed);
+                return;
+            }
+        }
+    }
+}
+
+impl Transport for SimulatedTransport {
+    fn send(&mut self, data: &[u8]) -> io::Result<usize> {
+        Self::schedule(&self.conditions, &mut self.random, &mut self.outgoing, data);
+        self.flush();
+
+        Ok(data.len())
+    }
+
+    fn recv(&mut self) -> io::Result<Option<Vec<u8>>> {
+        self.flush();
+
+        while let Some(data) = self.inner.recv()? {
+            Self::schedule(
+                &self.conditions,
+                &mut self.random,
+                &mut self.incoming,
+                &data,
+            );
+        }
+
+        match self.incoming.first() {
+            Some(delayed) if delayed.due <= Instant::now() => {
+                Ok(Some(self.incoming.remove(0).data))
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::{Arc, Mutex};
+
+    /// fails its first sends, then keeps what it is given
+    struct FlakyTransport {
+        failures: usize,
+        sent: Arc<Mutex<Vec<Vec<u8>>>>,
+    }
+
+    impl Transport for FlakyTransport {
+        fn send(&mut self, data: &[u8]) -> io::Result<usize> {
+            if self.failures > 0 {
+                self.failures -= 1;
+                return Err(ErrorKind::WouldBlock.into());
+            }
+
+            self.sent.lock().unwrap().push(data.to_vec());
+            Ok(data.len())
+        }
+
+        fn recv(&mut self) -> io::Result<Option<Vec<u8>>> {
+            Ok(None)
+        }
+    }
+
+    #[test]
+    fn datagrams_are_kept_when_sending_fails() {
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let inner = FlakyTransport {
+            failures: 2,
+            sent: sent.clone(),
+        };
+        let mut transport = SimulatedTransport::new(Box::new(inner), NetworkConditions::default());
+
+        // queued, so they will still go out
+        assert_eq!(transport.send(&[1]).unwrap(), 1);
+        assert_eq!(transport.send(&[2]).unwrap(), 1);
+        assert_eq!(transport.outgoing.len(), 2);
+
+        assert!(transport.recv().unwrap().is_none());
+        assert!(transport.outgoing.is_empty());
+        assert_eq!(*sent.lock().unwrap(), vec![vec![1], vec![2]]);
+    }
+
+    #[test]
+    fn failed_sends_are_not_reported_when_receiving() {
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let inner = FlakyTransport {
+            failures: 3,
+            sent: sent.clone(),
+        };
+        let mut transport = SimulatedTransport::new(Box::new(inner), NetworkConditions::default());
+
+        transport.send(&[1]).unwrap();
+        assert!(transport.recv().unwrap().is_none());
+        assert!(transport.recv().unwrap().is_none());
+        assert_eq!(transport.outgoing.len(), 1);
+
+        assert!(transport.recv().unwrap().is_none());
+        assert_eq!(*sent.lock().unwrap(), vec![vec![1]]);
+    }
+
+    #[test]
+    fn probabilities_must_be_between_0_and_1() {
+        let mut conditions = NetworkConditions::default();
+        assert!(conditions.validate().is_ok());
+
+        conditions.loss = 1.0;
+        assert!(conditions.validate().is_ok());
+
+        conditions.duplication = 1.5;
+        assert!(conditions.validate().is_err());
+
+        conditions.duplication = 0.0;
+        conditions.reordering = -0.1;
+        assert!(conditions.validate().is_err());
+    }
+}
@@ -0,0 +1,125 @@
+use crate::network::transport::Transport;
+
+use std::convert::TryInto;
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+
+const LENGTH_SIZE: usize = 4;
+const MAX_FRAME_SIZE: usize = 65535;
+/// bytes waiting for the socket before new datagrams are dropped, like a full UDP send buffer
+const MAX_OUTGOING_SIZE: usize = 256 * 1024;
+
+/// Stream transport for networks that block UDP. Every datagram is written as a big endian
+/// `u32` length followed by its content.
+pub struct TcpTransport {
+    stream: TcpStream,
+    incoming: Vec<u8>,
+    outgoing: Vec<u8>,
+}
+
+impl TcpTransport {
+    pub fn connect<A: ToSocketAddrs>(address: A) -> io::Result<Self> {
+        let stream = TcpStream::connect(address)?;
+        stream.set_nodelay(true)?;
+        stream.set_nonblocking(true)?;
+
+        Ok(Self {
+            stream,
+            incoming: Vec::new(),
+            outgoing: Vec::new(),
+        })
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        while !self.outgoing.is_empty() {
+            match self.stream.write(&self.outgoing) {
+                Ok(0) => return Err(ErrorKind::WriteZero.into()),
+                Ok(size) => {
+                    self.outgoing.drain(..size);
+                }
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Transport for TcpTransport {
+    fn send(&mut self, data: &[u8]) -> io::Result<usize> {
+        if data.len() > MAX_FRAME_SIZE {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                "datagram too large",
+            ));
+        }
+
+        // the channel resends what matters, queuing more would only add latency
+        self.flush()?;
+        if self.outgoing.len() + LENGTH_SIZE + data.len() > MAX_OUTGOING_SIZE {
+            return Err(io::Error::new(ErrorKind::WouldBlock, "send buffer full"));
+        }
+
+        self.outgoing
+            .extend_from_slice(&(data.len() as u32).to_be_bytes());
+        self.outgoing.extend_from_slice(data);
+        self.flush()?;
+
+        Ok(data.len())
+    }
+
+    fn recv(&mut self) -> io::Result<Option<Vec<u8>>> {
+        self.flush()?;
+
+        let mut buffer = [0; MAX_FRAME_SIZE];
+        loop {
+            match self.stream.read(&mut buffer) {
+                Ok(0) => return Err(ErrorKind::ConnectionAborted.into()),
+                Ok(size) => self.incoming.extend_from_slice(&buffer[..size]),
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                Err(err) => return Err(err),
+            }
+        }
+
+        if self.incoming.len() < LENGTH_SIZE {
+            return Ok(None);
+        }
+
+        let length = u32::from_be_bytes(self.incoming[..LENGTH_SIZE].try_into().unwrap()) as usize;
+        if length > MAX_FRAME_SIZE {
+            return Err(io::Error::new(ErrorKind::InvalidData, "frame too large"));
+        }
+
+        if self.incoming.len() < LENGTH_SIZE + length {
+            return Ok(None);
+        }
+
+        let frame = self.incoming[LENGTH_SIZE..LENGTH_SIZE + length].to_vec();
+        self.incoming.drain(..LENGTH_SIZE + length);
+
+        Ok(Some(frame))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::net::TcpListener;
+
+    #[test]
+    fn outgoing_datagrams_are_dropped_when_the_peer_stops_reading() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut transport = TcpTransport::connect(listener.local_addr().unwrap()).unwrap();
+        let (_peer, _) = listener.accept().unwrap();
+
+        let datagram = vec![0; MAX_FRAME_SIZE];
+        let error = (0..10_000)
+            .find_map(|_| transport.send(&datagram).err())
+            .expect("the socket never filled up");
+
+        assert_eq!(error.kind(), ErrorKind::WouldBlock);
+        assert!(transport.outgoing.len() <= MAX_OUTGOING_SIZE);
+    }
+}
@@ -0,0 +1,80 @@
+use crate::network::transport::Transport;
+
+use std::io::{self, ErrorKind};
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+
+const MAX_DATAGRAM_SIZE: usize = 65535;
+
+pub struct UdpTransport {
+    socket: UdpSocket,
+    /// the other end, learned from the first datagram when listening
+    peer: Option<SocketAddr>,
+}
+
+impl UdpTransport {
+    /// tries every resolved address until a socket of the same family can be bound for it
+    pub fn connect<A: ToSocketAddrs>(address: A) -> io::Result<Self> {
+        let mut last_error = io::Error::new(ErrorKind::InvalidInput, "no address to connect to");
+
+        for peer in address.to_socket_addrs()? {
+            let local = if peer.is_ipv4() {
+                "0.0.0.0:0"
+            } else {
+                "[::]:0"
+            };
+
+            match UdpSocket::bind(local) {
+                Ok(socket) => {
+                    socket.set_nonblocking(true)?;
+
+                    return Ok(Self {
+                        socket,
+                        peer: Some(peer),
+                    });
+                }
+                Err(err) => last_error = err,
+            }
+        }
+
+        Err(last_error)
+    }
+
+    /// waits for a single peer on the given address
+    pub fn listen<A: ToSocketAddrs>(address: A) -> io::Result<Self> {
+        let socket = UdpSocket::bind(address)?;
+        socket.set_nonblocking(true)?;
+
+        Ok(Self { socket, peer: None })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+}
+
+impl Transport for UdpTransport {
+    fn send(&mut self, data: &[u8]) -> io::Result<usize> {
+        match self.peer {
+            Some(peer) => self.socket.send_to(data, peer),
+            None => Err(io::Error::new(ErrorKind::NotConnected, "no peer yet")),
+        }
+    }
+
+    fn recv(&mut self) -> io::Result<Option<Vec<u8>>> {
+        loop {
+            let mut data = [0; MAX_DATAGRAM_SIZE];
+            let (size, address) = match self.socket.recv_from(&mut data) {
+                Ok(result) => result,
+                Err(err) if err.kind() == ErrorKind::WouldBlock => return Ok(None),
+                Err(err) => return Err(err),
+            };
+
+            if self.peer.map_or(false, |peer| peer != address) {
+                continue;
+            }
+
+            self.peer = Some(address);
+            return Ok(Some(data[..size].to_vec()));
+        }
+    }
+}
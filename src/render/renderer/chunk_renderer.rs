@@ -21,17 +21,10 @@ use std::sync::mpsc::{channel, Receiver, Sender};
 #[cfg(feature = "watchers")]
 use crate::utils::watcher::*;
 
-// TODO: remove the dependancy to glutin from this file.
-use crate::input::InputHandler;
-use glutin::event::VirtualKeyCode;
+use crate::input::{Action, InputHandler};
 
 const TEXTURE_RESOLUTION: u32 = 16;
 const MIN_RENDER_DISTANCE: u8 = 2;
-const FOG: Vector3 = Vector3 {
-    x: 0.62,
-    y: 0.76,
-    z: 1.0,
-};
 
 fn load_textures() -> TextureArray {
     let database = TextureDatabase::new();
@@ -61,6 +54,8 @@ pub struct ChunkRenderer {
     chunk_loading_chan: ChunkLoadingChannel,
     threadpool: ThreadPool,
     loading_chunks: HashSet<ChunkGridCoordinate>,
+    /// chunks whose blocks changed, their current mesh is kept until the new one is ready
+    dirty_chunks: HashSet<ChunkGridCoordinate>,
 
     #[cfg(feature = "watchers")]
     texture_watcher: Watcher,
@@ -195,6 +190,7 @@ impl ChunkRenderer {
                 chunk_loading_chan: channel(),
                 threadpool: ThreadPool::new(1),
                 loading_chunks: HashSet::new(),
+                dirty_chunks: HashSet::new(),
 
                 #[cfg(feature = "watchers")]
                 texture_watcher: Watcher::new(
@@ -210,12 +206,23 @@ impl ChunkRenderer {
         }
     }
 
+    /// regenerates the mesh of a chunk once its blocks changed
+    pub fn invalidate(&mut self, coords: ChunkGridCoordinate) {
+        if self.meshes.contains_key(&coords) || self.loading_chunks.contains(&coords) {
+            self.dirty_chunks.insert(coords);
+        }
+    }
+
     pub fn update(&mut self, world: &World, input: &InputHandler) {
-        if input.just_pressed(VirtualKeyCode::J) && self.render_distance > MIN_RENDER_DISTANCE {
+        if input.action_just_pressed(Action::DecreaseRenderDistance)
+            && self.render_distance > MIN_RENDER_DISTANCE
+        {
             self.render_distance -= 1;
         }
 
-        if input.just_pressed(VirtualKeyCode::K) && self.render_distance < LOAD_DISTANCE {
+        if input.action_just_pressed(Action::IncreaseRenderDistance)
+            && self.render_distance < LOAD_DISTANCE
+        {
             self.render_distance += 1;
         }
 
@@ -234,10 +241,15 @@ impl ChunkRenderer {
         // remove unloaded chunk
         self.meshes
             .retain(|coords, _| world.chunks.contains_key(coords));
+        self.dirty_chunks
+            .retain(|coords| world.chunks.contains_key(coords));
 
-        // generate missing geometry for loaded chunks
+        // generate missing geometry for loaded chunks, a chunk still loading is regenerated
+        // once its current mesh arrives
         for coords in world.chunks.keys() {
-            if !self.meshes.contains_key(coords) && !self.loading_chunks.contains(coords) {
+            let missing = !self.meshes.contains_key(coords) || self.dirty_chunks.contains(coords);
+
+            if missing && !self.loading_chunks.contains(coords) {
                 let chunk_group = world.get_chunk_group(*coords);
 
                 if chunk_group.is_none() {
@@ -254,11 +266,13 @@ impl ChunkRenderer {
                 });
 
                 self.loading_chunks.insert(*coords);
+                self.dirty_chunks.remove(coords);
             }
         }
     }
 
-    pub fn draw<C: Camera>(&self, camera: &C) {
+    /// `fog` is the color far chunks fade into, usually the sky color
+    pub fn draw<C: Camera>(&self, camera: &C, fog: Vector3) {
         self.program.use_program();
         self.program
             .set_uniform_m4("projection_view", camera.projection_view());
@@ -266,7 +280,7 @@ impl ChunkRenderer {
             .set_uniform_texture("diffuse_textures", self.textures.unit());
         self.program
             .set_uniform_v3("camera_position", camera.position());
-        self.program.set_uniform_v3("fog_color", FOG);
+        self.program.set_uniform_v3("fog_color", fog);
         self.program
             .set_uniform_u32("render_distance", self.render_distance as u32);
 
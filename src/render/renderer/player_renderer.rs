@@ -4,8 +4,44 @@ use crate::render::camera::Camera;
 use crate::render::mesh::PlayerMesh;
 use crate::render::shaders::ShaderProgram;
 
+use math::matrix::Matrix4;
 use math::vector::Vector3;
 
+/// point of the mesh the player looks from and rotates around
+const EYE: Vector3 = Vector3 {
+    x: 0.5,
+    y: 1.5,
+    z: 0.5,
+};
+
+/// places the mesh at `position`, looking toward `euler_angles`, yaw first then pitch
+fn model(position: Vector3, euler_angles: Vector3) -> Matrix4 {
+    let (sin_pitch, cos_pitch) = euler_angles.x.to_radians().sin_cos();
+    let (sin_yaw, cos_yaw) = euler_angles.y.to_radians().sin_cos();
+
+    let rotation = [
+        [cos_yaw, sin_yaw * sin_pitch, sin_yaw * cos_pitch],
+        [0.0, cos_pitch, -sin_pitch],
+        [-sin_yaw, cos_yaw * sin_pitch, cos_yaw * cos_pitch],
+    ];
+    let position = [position.x, position.y, position.z];
+    let eye = [EYE.x, EYE.y, EYE.z];
+
+    let mut m = Matrix4::identity();
+    for row in 0..3 {
+        for column in 0..3 {
+            m[row][column] = rotation[row][column];
+        }
+
+        m[row][3] = position[row]
+            - (0..3)
+                .map(|column| rotation[row][column] * eye[column])
+                .sum::<f32>();
+    }
+
+    m
+}
+
 pub struct PlayerRenderer {
     program: ShaderProgram,
     mesh: PlayerMesh,
@@ -18,11 +54,11 @@ impl PlayerRenderer {
 
             layout (location=0) in vec3 position;
 
-            uniform vec3 world_position;
+            uniform mat4 model;
             uniform mat4 projection_view; // projection * view
 
             void main() {
-                gl_Position = projection_view * vec4(world_position + position, 1.0);
+                gl_Position = projection_view * model * vec4(position, 1.0);
             }
         "#;
 
@@ -62,15 +98,8 @@ impl PlayerRenderer {
             for player in players.iter() {
                 // TODO: add camera frustum check
 
-                self.program.set_uniform_v3(
-                    "world_position",
-                    player.position()
-                        - Vector3 {
-                            x: 0.5,
-                            y: 1.5,
-                            z: 0.5,
-                        },
-                );
+                self.program
+                    .set_uniform_m4("model", &model(player.position(), player.euler_angles()));
 
                 self.mesh.draw();
             }
@@ -1,4 +1,4 @@
-use crate::game::entity::Player;
+use crate::game::{entity::Player, Chat, PlayerListEntry, WorldClock};
 use crate::input::InputHandler;
 use crate::ops::Bindable;
 use crate::render::{
@@ -8,6 +8,7 @@ use crate::render::{
     renderer::{ChunkRenderer, PlayerRenderer, UIRenderer},
 };
 
+use core::chunk::ChunkGridCoordinate;
 use core::world::World;
 use math::vector::Vector3;
 
@@ -16,6 +17,16 @@ pub const SKY_COLOR: Vector3 = Vector3 {
     y: 0.76,
     z: 1.0,
 };
+pub const NIGHT_SKY_COLOR: Vector3 = Vector3 {
+    x: 0.02,
+    y: 0.03,
+    z: 0.08,
+};
+
+/// sky color for the current time of day, the fog fades into it
+fn sky_color(clock: &WorldClock) -> Vector3 {
+    NIGHT_SKY_COLOR + (SKY_COLOR - NIGHT_SKY_COLOR) * clock.daylight()
+}
 
 pub struct Renderer {
     framebuffer: FrameBuffer,
@@ -49,24 +60,40 @@ impl Renderer {
         self.ui_renderer.resize(width, height);
     }
 
+    /// remeshes chunks after their blocks changed
+    pub fn invalidate_chunks(&mut self, chunks: Vec<ChunkGridCoordinate>) {
+        for coords in chunks {
+            self.chunk_renderer.invalidate(coords);
+        }
+    }
+
     pub fn update(&mut self, world: &World, input: &InputHandler) {
         self.chunk_renderer.update(world, input);
     }
 
-    pub fn draw<C: Camera>(&self, camera: &C, players: &Vec<&Player>) {
+    pub fn draw<C: Camera>(
+        &self,
+        camera: &C,
+        players: &Vec<&Player>,
+        chat: &Chat,
+        clock: &WorldClock,
+        player_list: Option<&[PlayerListEntry]>,
+    ) {
+        let sky = sky_color(clock);
+
         self.framebuffer.bind();
 
         unsafe {
-            gl::ClearColor(SKY_COLOR.x, SKY_COLOR.y, SKY_COLOR.z, 1.0);
+            gl::ClearColor(sky.x, sky.y, sky.z, 1.0);
         }
 
         self.framebuffer.clear(true, true, false);
 
-        self.chunk_renderer.draw(camera);
+        self.chunk_renderer.draw(camera, sky);
         self.player_renderer.draw(camera, players);
 
         self.post_pipeline.apply(&self.framebuffer);
 
-        self.ui_renderer.draw();
+        self.ui_renderer.draw(chat, player_list);
     }
 }
@@ -1,15 +1,96 @@
+use crate::game::{Chat, ConnectionQuality, PlayerListEntry};
+use crate::input::LineBuffer;
 use crate::ops::{Bindable, Drawable};
 use crate::render::{
-    camera::OrthographicProjection, mesh::TextureQuad, shaders::ShaderProgram, ui::Font,
+    camera::OrthographicProjection,
+    mesh::TextureQuad,
+    shaders::ShaderProgram,
+    ui::{Font, Rect},
 };
 
-use math::vector::Vector3;
+use math::utils::next_power_of_two;
+use math::vector::{Vector2, Vector3};
 use std::path::Path;
+use std::time::Duration;
+
+/// size the font textures are rasterized at
+const FONT_SIZE: f32 = 128.0;
+const TEXT_SIZE: f32 = 20.0;
+const LINE_HEIGHT: f32 = 24.0;
+const CHAT_MARGIN: f32 = 16.0;
+const CHAT_WIDTH: f32 = 640.0;
+const CHAT_MAX_LINES: usize = 10;
+/// long messages are wrapped after this many characters
+const CHAT_LINE_LENGTH: usize = 60;
+/// how long a message stays on screen before it starts fading out
+const CHAT_DISPLAY_TIME: Duration = Duration::from_secs(10);
+const CHAT_FADE_TIME: Duration = Duration::from_secs(1);
+const BACKGROUND_OPACITY: f32 = 0.4;
+const PLAYER_LIST_WIDTH: f32 = 480.0;
+const PLAYER_LIST_PADDING: f32 = 16.0;
+/// x offsets of the ping and quality columns from the left of the panel
+const PING_COLUMN: f32 = 280.0;
+const QUALITY_COLUMN: f32 = 380.0;
+const WHITE: Vector3 = Vector3 {
+    x: 1.0,
+    y: 1.0,
+    z: 1.0,
+};
+const BLACK: Vector3 = Vector3 {
+    x: 0.0,
+    y: 0.0,
+    z: 0.0,
+};
+const SELECTION_COLOR: Vector3 = Vector3 {
+    x: 0.25,
+    y: 0.45,
+    z: 0.9,
+};
+const GREY: Vector3 = Vector3 {
+    x: 0.7,
+    y: 0.7,
+    z: 0.7,
+};
+
+fn quality_color(quality: ConnectionQuality) -> Vector3 {
+    match quality {
+        ConnectionQuality::Good => Vector3 {
+            x: 0.3,
+            y: 0.9,
+            z: 0.3,
+        },
+        ConnectionQuality::Fair => Vector3 {
+            x: 0.95,
+            y: 0.8,
+            z: 0.2,
+        },
+        ConnectionQuality::Poor => Vector3 {
+            x: 0.95,
+            y: 0.3,
+            z: 0.3,
+        },
+        ConnectionQuality::Unknown => GREY,
+    }
+}
+
+/// splits a message in lines of at most `CHAT_LINE_LENGTH` characters
+fn wrap(text: &str) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+
+    chars
+        .chunks(CHAT_LINE_LENGTH)
+        .map(|line| line.iter().collect())
+        .collect()
+}
 
 pub struct UIRenderer {
     program: ShaderProgram,
     projection: OrthographicProjection,
     font: Font,
+    /// unit quad stretched over each glyph and background
+    quad: TextureQuad,
+    width: f32,
+    height: f32,
 }
 
 impl UIRenderer {
@@ -23,10 +104,13 @@ impl UIRenderer {
             out vec2 uv;
 
             uniform mat4 projection;
+            uniform vec2 offset;
+            uniform vec2 size;
+            uniform vec2 uv_scale;
 
             void main() {
-                uv = uv_data;
-                gl_Position = projection * vec4(position, 1.0);
+                uv = uv_data * uv_scale;
+                gl_Position = projection * vec4(offset + position.xy * size, 0.0, 1.0);
             }
         "#;
 
@@ -41,16 +125,17 @@ impl UIRenderer {
             uniform vec3 background_color;
             uniform vec3 tint_color;
             uniform bool render_texture;
+            uniform float opacity;
 
             void main() {
                 if (!render_texture) {
-                    color = vec4(background_color, 1.0);
+                    color = vec4(background_color, opacity);
                 } else {
                     color = texture(diffuse_texture, uv);
                     color = vec4(color.r * tint_color.r, 
                                  color.r * tint_color.g, 
                                  color.r * tint_color.b, 
-                                 color.r * 1.0);
+                                 color.r * opacity);
                 }
 
                 if (color.a < 0.01) {
@@ -62,7 +147,10 @@ impl UIRenderer {
         match ShaderProgram::new(vertex_src, fragment_src) {
             Ok(program) => Self {
                 program,
-                font: Font::new(Path::new("res/fonts/nunito-regular.ttf"), 128.0).unwrap(),
+                font: Font::new(Path::new("res/fonts/nunito-regular.ttf"), FONT_SIZE).unwrap(),
+                quad: TextureQuad::new_rect(0.0, 0.0, 1.0, 1.0),
+                width: width as f32,
+                height: height as f32,
                 projection: OrthographicProjection::new(
                     0.0,
                     width as f32,
@@ -81,9 +169,12 @@ impl UIRenderer {
 
     pub fn resize(&mut self, width: usize, height: usize) {
         self.projection.resize(width, height);
+        self.width = width as f32;
+        self.height = height as f32;
     }
 
-    pub fn draw(&self) {
+    /// `player_list` is drawn over everything else when given
+    pub fn draw(&self, chat: &Chat, player_list: Option<&[PlayerListEntry]>) {
         self.program.use_program();
         self.program
             .set_uniform_m4("projection", self.projection.matrix());
@@ -93,29 +184,218 @@ impl UIRenderer {
             gl::Enable(gl::BLEND);
         }
 
-        for (rect, texture) in self.font.iter_for(&String::from("Hello World!")) {
-            let quad =
-                TextureQuad::new_rect(rect.x + 300.0, rect.y + 300.0, rect.width, rect.height);
+        self.quad.bind();
+        self.draw_chat(chat);
 
-            self.program.set_uniform_bool("render_texture", true);
-            self.program
-                .set_uniform_texture("diffuse_texture", texture.unit());
+        if let Some(entries) = player_list {
+            self.draw_player_list(entries);
+        }
+
+        self.quad.unbind();
+
+        unsafe {
+            gl::Disable(gl::BLEND);
+        }
+    }
+
+    /// recent messages above the line being typed, messages fade out unless the chat is open
+    fn draw_chat(&self, chat: &Chat) {
+        let typing = chat.draft().is_some();
+        let mut lines: Vec<(String, f32)> = Vec::new();
+
+        for message in chat.history().iter().rev() {
+            let age = message.received.elapsed();
+            let opacity = if typing || age < CHAT_DISPLAY_TIME {
+                1.0
+            } else {
+                1.0 - (age - CHAT_DISPLAY_TIME).as_secs_f32() / CHAT_FADE_TIME.as_secs_f32()
+            };
+
+            if opacity <= 0.0 {
+                break;
+            }
+
+            let text = match message.name.as_ref() {
+                Some(name) => format!("<{}> {}", name, message.text),
+                None => message.text.clone(),
+            };
+
+            for line in wrap(&text).into_iter().rev() {
+                lines.push((line, opacity));
+            }
+
+            if lines.len() >= CHAT_MAX_LINES {
+                break;
+            }
+        }
+
+        lines.truncate(CHAT_MAX_LINES);
+
+        let mut baseline = self.height - CHAT_MARGIN;
+
+        if let Some(draft) = chat.draft() {
+            self.draw_draft(draft, baseline);
+            baseline -= LINE_HEIGHT;
+        }
+
+        for (line, opacity) in lines.iter() {
+            self.draw_background(baseline, *opacity);
+            self.draw_text(line, CHAT_MARGIN, baseline, WHITE, *opacity);
+            baseline -= LINE_HEIGHT;
+        }
+    }
+
+    /// line being typed with its cursor and selection, scrolled so the cursor stays visible
+    fn draw_draft(&self, draft: &LineBuffer, baseline: f32) {
+        let chars: Vec<char> = draft.text().chars().collect();
+        let start = draft.cursor().saturating_sub(CHAT_LINE_LENGTH);
+        let end = (start + CHAT_LINE_LENGTH).min(chars.len());
+        let left = CHAT_MARGIN + self.text_width("> ");
 
-            self.program.set_uniform_v3(
-                "tint_color",
-                Vector3 {
-                    x: 0.0,
-                    y: 0.0,
-                    z: 0.0,
-                },
+        // x of the given position in the line, clamped to the visible part
+        let x = |position: usize| {
+            let position = position.max(start).min(end);
+            left + self.text_width(&chars[start..position].iter().collect::<String>())
+        };
+
+        self.draw_background(baseline, 1.0);
+
+        if let Some((selection_start, selection_end)) = draft.selection() {
+            let from = x(selection_start);
+            let rect = Rect::new(
+                from,
+                baseline - LINE_HEIGHT * 0.75,
+                x(selection_end) - from,
+                LINE_HEIGHT,
             );
+            self.draw_rect(rect, SELECTION_COLOR, 1.0);
+        }
 
-            texture.bind();
-            // quad.draw();
+        let visible: String = chars[start..end].iter().collect();
+        self.draw_text("> ", CHAT_MARGIN, baseline, WHITE, 1.0);
+        self.draw_text(&visible, left, baseline, WHITE, 1.0);
+
+        let cursor = Rect::new(
+            x(draft.cursor()),
+            baseline - TEXT_SIZE * 0.8,
+            2.0,
+            TEXT_SIZE,
+        );
+        self.draw_rect(cursor, WHITE, 1.0);
+    }
+
+    /// table of the connected players centered at the top of the screen
+    fn draw_player_list(&self, entries: &[PlayerListEntry]) {
+        let height = LINE_HEIGHT * (entries.len() + 1) as f32 + PLAYER_LIST_PADDING * 2.0;
+        let left = ((self.width - PLAYER_LIST_WIDTH) / 2.0).max(0.0);
+        let top = PLAYER_LIST_PADDING * 2.0;
+
+        self.draw_panel(Rect::new(left, top, PLAYER_LIST_WIDTH, height), 1.0);
+
+        let x = left + PLAYER_LIST_PADDING;
+        let mut baseline = top + PLAYER_LIST_PADDING + LINE_HEIGHT * 0.75;
+
+        self.draw_text("Player", x, baseline, GREY, 1.0);
+        self.draw_text("Ping", x + PING_COLUMN, baseline, GREY, 1.0);
+        self.draw_text("Quality", x + QUALITY_COLUMN, baseline, GREY, 1.0);
+
+        for entry in entries.iter() {
+            baseline += LINE_HEIGHT;
+
+            let ping = match entry.latency {
+                Some(latency) => format!("{}ms", latency),
+                None => String::from("-"),
+            };
+            let quality = entry.quality();
+
+            self.draw_text(&entry.name, x, baseline, WHITE, 1.0);
+            self.draw_text(&ping, x + PING_COLUMN, baseline, WHITE, 1.0);
+            self.draw_text(
+                &quality.to_string(),
+                x + QUALITY_COLUMN,
+                baseline,
+                quality_color(quality),
+                1.0,
+            );
         }
+    }
 
-        unsafe {
-            gl::Disable(gl::BLEND);
+    /// translucent strip behind the line of chat on `baseline`
+    fn draw_background(&self, baseline: f32, opacity: f32) {
+        self.draw_panel(
+            Rect::new(
+                CHAT_MARGIN / 2.0,
+                baseline - LINE_HEIGHT * 0.75,
+                CHAT_WIDTH,
+                LINE_HEIGHT,
+            ),
+            opacity,
+        );
+    }
+
+    fn draw_panel(&self, rect: Rect, opacity: f32) {
+        self.draw_rect(rect, BLACK, BACKGROUND_OPACITY * opacity);
+    }
+
+    fn draw_rect(&self, rect: Rect, color: Vector3, opacity: f32) {
+        self.program.set_uniform_bool("render_texture", false);
+        self.program.set_uniform_v3("background_color", color);
+        self.program.set_uniform_f32("opacity", opacity);
+        self.set_rect(rect, Vector2 { x: 1.0, y: 1.0 });
+        self.quad.draw();
+    }
+
+    fn text_width(&self, text: &str) -> f32 {
+        self.font.width(text) * TEXT_SIZE / FONT_SIZE
+    }
+
+    /// draws a line of text starting at `x` on `baseline`
+    fn draw_text(&self, text: &str, x: f32, baseline: f32, color: Vector3, opacity: f32) {
+        let scale = TEXT_SIZE / FONT_SIZE;
+        let text = String::from(text);
+
+        self.program.set_uniform_bool("render_texture", true);
+        self.program.set_uniform_v3("tint_color", color);
+        self.program.set_uniform_f32("opacity", opacity);
+
+        for (rect, texture) in self.font.iter_for(&text) {
+            let uv_scale = Vector2 {
+                x: rect.width / next_power_of_two(rect.width as u32) as f32,
+                y: rect.height / next_power_of_two(rect.height as u32) as f32,
+            };
+
+            self.program
+                .set_uniform_texture("diffuse_texture", texture.unit());
+            texture.bind();
+
+            self.set_rect(
+                Rect::new(
+                    x + rect.x * scale,
+                    baseline + rect.y * scale,
+                    rect.width * scale,
+                    rect.height * scale,
+                ),
+                uv_scale,
+            );
+            self.quad.draw();
         }
     }
+
+    fn set_rect(&self, rect: Rect, uv_scale: Vector2) {
+        self.program.set_uniform_v2(
+            "offset",
+            Vector2 {
+                x: rect.x,
+                y: rect.y,
+            },
+        );
+        self.program.set_uniform_v2(
+            "size",
+            Vector2 {
+                x: rect.width,
+                y: rect.height,
+            },
+        );
+        self.program.set_uniform_v2("uv_scale", uv_scale);
+    }
 }
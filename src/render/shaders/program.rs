@@ -54,6 +54,10 @@ impl ShaderProgram {
         unsafe { gl::Uniform1i(self.get_uniform_location(name), value as i32) }
     }
 
+    pub fn set_uniform_f32(&self, name: &str, value: f32) {
+        unsafe { gl::Uniform1f(self.get_uniform_location(name), value) }
+    }
+
     pub fn set_uniform_u32(&self, name: &str, value: u32) {
         unsafe { gl::Uniform1ui(self.get_uniform_location(name), value) }
     }
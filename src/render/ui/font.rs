@@ -84,6 +84,14 @@ impl Font {
         }
     }
 
+    /// horizontal distance covered by the text at the size the font was loaded
+    pub fn width(&self, text: &str) -> f32 {
+        text.chars()
+            .filter_map(|c| self.chars(c).or_else(|| self.chars('\u{0}')))
+            .map(|font_char| font_char.advance)
+            .sum()
+    }
+
     pub fn chars<'a>(&'a self, c: char) -> Option<&'a FontCharacter> {
         self.chars.get(&c)
     }